@@ -505,13 +505,14 @@ async fn test_chat_function_json_override_with_mode_strict() {
     test_chat_function_json_override_with_mode(ModelInferenceRequestJsonMode::Strict).await;
 }
 
+// `basic_test` has no `output_schema`, so requesting `implicit_tool` mode for
+// it is still rejected: there is nothing to coerce the output into.
 #[cfg(feature = "e2e_tests")]
 #[tokio::test]
-async fn test_chat_function_json_override_with_mode_implicit_tool() {
+async fn test_chat_function_json_override_with_mode_implicit_tool_no_schema() {
     let client = Client::new();
     let episode_id = Uuid::now_v7();
 
-    // Note that we need to include 'json' somewhere in the messages, to stop OpenAI from complaining
     let payload = json!({
         "function_name": "basic_test",
         "variant_name": "openai",
@@ -549,11 +550,71 @@ async fn test_chat_function_json_override_with_mode_implicit_tool() {
     assert_eq!(
         response_json,
         serde_json::json!({
-            "error": "JSON mode `implicit_tool` is not supported for chat functions"
+            "error": "JSON mode `implicit_tool` requires `basic_test` to declare an output_schema"
         })
     );
 }
 
+// `basic_test_with_schema` carries an `output_schema`, so `implicit_tool`
+// mode should succeed: the gateway forces a single structured-output tool
+// call and returns a structured content block alongside the raw text.
+#[cfg(feature = "e2e_tests")]
+#[tokio::test]
+async fn test_chat_function_json_override_with_mode_implicit_tool() {
+    let client = Client::new();
+    let episode_id = Uuid::now_v7();
+
+    // Note that we need to include 'json' somewhere in the messages, to stop OpenAI from complaining
+    let payload = json!({
+        "function_name": "basic_test_with_schema",
+        "variant_name": "openai",
+        "episode_id": episode_id,
+        "input":
+            {"system": {"assistant_name": "AskJeeves"},
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "What is the capital of Japan (possibly as JSON)?"
+                }
+            ]},
+        "params": {
+            "chat_completion": {
+                "json_mode": "implicit_tool",
+            }
+        },
+        "stream": false,
+    });
+
+    let response = client
+        .post(get_gateway_endpoint("/inference"))
+        .json(&payload)
+        .send()
+        .await
+        .unwrap();
+    let response_status = response.status();
+    let response_json = response.json::<Value>().await.unwrap();
+    assert_eq!(
+        response_status,
+        StatusCode::OK,
+        "Unexpected response status, body: {response_json:?})"
+    );
+    let content_blocks = response_json.get("content").unwrap().as_array().unwrap();
+    // The raw text block is preserved alongside the structured output block.
+    let text_block = content_blocks
+        .iter()
+        .find(|block| block.get("type").unwrap().as_str().unwrap() == "text")
+        .expect("response should still contain a text block");
+    let content = text_block.get("text").unwrap().as_str().unwrap();
+    assert!(content.contains("Tokyo"), "Content should mention Tokyo");
+
+    let structured_block = content_blocks
+        .iter()
+        .find(|block| block.get("type").unwrap().as_str().unwrap() == "structured")
+        .expect("response should contain a structured output block");
+    let parsed = structured_block.get("value").unwrap();
+    assert!(parsed.get("answer").is_some());
+}
+
 #[cfg_attr(feature = "batch_tests", allow(unused))]
 async fn test_chat_function_json_override_with_mode(json_mode: ModelInferenceRequestJsonMode) {
     let client = Client::new();
@@ -840,21 +901,18 @@ async fn test_embedding_request() {
 
     let client = Client::new();
     let request = EmbeddingRequest {
-        input: "This is a test input".to_string(),
+        input: "This is a test input".to_string().into(),
     };
     let api_keys = InferenceCredentials::default();
     let response = provider_config
         .embed(&request, &client, &api_keys)
         .await
         .unwrap();
-    assert_eq!(response.embedding.len(), 1536);
+    assert_eq!(response.embeddings.len(), 1);
+    let embedding = &response.embeddings[0].vector;
+    assert_eq!(embedding.len(), 1536);
     // Calculate the L2 norm of the embedding
-    let norm: f32 = response
-        .embedding
-        .iter()
-        .map(|&x| x.powi(2))
-        .sum::<f32>()
-        .sqrt();
+    let norm: f32 = embedding.iter().map(|&x| x.powi(2)).sum::<f32>().sqrt();
 
     // Assert that the norm is approximately 1 (allowing for small floating-point errors)
     assert!(
@@ -905,15 +963,17 @@ async fn test_embedding_sanity_check() {
         .expect("Failed to deserialize EmbeddingProviderConfig");
     let client = Client::new();
     let embedding_request_a = EmbeddingRequest {
-        input: "Joe Biden is the president of the United States".to_string(),
+        input: "Joe Biden is the president of the United States".to_string().into(),
     };
 
     let embedding_request_b = EmbeddingRequest {
-        input: "Kamala Harris is the vice president of the United States".to_string(),
+        input: "Kamala Harris is the vice president of the United States"
+            .to_string()
+            .into(),
     };
 
     let embedding_request_c = EmbeddingRequest {
-        input: "My favorite systems programming language is Rust".to_string(),
+        input: "My favorite systems programming language is Rust".to_string().into(),
     };
     let api_keys = InferenceCredentials::default();
 
@@ -928,11 +988,14 @@ async fn test_embedding_sanity_check() {
     let response_a = response_a.expect("Failed to get embedding for request A");
     let response_b = response_b.expect("Failed to get embedding for request B");
     let response_c = response_c.expect("Failed to get embedding for request C");
+    let embedding_a = &response_a.embeddings[0].vector;
+    let embedding_b = &response_b.embeddings[0].vector;
+    let embedding_c = &response_c.embeddings[0].vector;
 
     // Calculate cosine similarities
-    let similarity_ab = cosine_similarity(&response_a.embedding, &response_b.embedding);
-    let similarity_ac = cosine_similarity(&response_a.embedding, &response_c.embedding);
-    let similarity_bc = cosine_similarity(&response_b.embedding, &response_c.embedding);
+    let similarity_ab = cosine_similarity(embedding_a, embedding_b);
+    let similarity_ac = cosine_similarity(embedding_a, embedding_c);
+    let similarity_bc = cosine_similarity(embedding_b, embedding_c);
 
     // Assert that semantically similar sentences have higher similarity (with a margin of 0.3)
     // We empirically determined this by staring at it (no science to it)
@@ -946,6 +1009,45 @@ async fn test_embedding_sanity_check() {
     );
 }
 
+#[cfg(feature = "e2e_tests")]
+#[tokio::test]
+async fn test_embedding_batch_request() {
+    let provider_config_serialized = r#"
+    type = "openai"
+    model_name = "text-embedding-3-small"
+    "#;
+    let provider_config: EmbeddingProviderConfig = toml::from_str(provider_config_serialized)
+        .expect("Failed to deserialize EmbeddingProviderConfig");
+    let client = Client::new();
+    let inputs = vec![
+        "This is the first input".to_string(),
+        "This is the second input".to_string(),
+        "This is the third input".to_string(),
+    ];
+    let request = EmbeddingRequest {
+        input: inputs.clone().into(),
+    };
+    let api_keys = InferenceCredentials::default();
+    let response = provider_config
+        .embed(&request, &client, &api_keys)
+        .await
+        .unwrap();
+
+    // One embedding per input, all from a single HTTP round trip.
+    assert_eq!(response.embeddings.len(), inputs.len());
+    for embedding in &response.embeddings {
+        let norm: f32 = embedding
+            .vector
+            .iter()
+            .map(|x| x.powi(2))
+            .sum::<f32>()
+            .sqrt();
+        assert!((norm - 1.0).abs() < 1e-6);
+    }
+    // Per-input token accounting is still preserved across the whole batch.
+    assert!(response.usage.input_tokens >= inputs.len() as u32);
+}
+
 #[cfg(feature = "e2e_tests")]
 fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
     let dot_product: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
@@ -0,0 +1,308 @@
+//! JWT-based caller authentication for multi-tenant gateway deployments.
+//!
+//! A request can carry a signed JWT (RS256/ES256) instead of, or alongside,
+//! static provider credentials. The token's claims are validated against the
+//! configured issuer/audience and mapped to the caller's identity and the
+//! set of provider API keys they're permitted to use, before any provider
+//! call is made.
+
+use std::collections::HashMap;
+
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+
+use crate::endpoints::inference::InferenceCredentials;
+use crate::error::{Error, ErrorDetails};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JwtAuthConfig {
+    pub algorithm: JwtAlgorithm,
+    /// PEM-encoded public key (RS256) or EC public key (ES256) used to
+    /// verify the token's signature. A JWKS endpoint is not fetched here;
+    /// operators that rotate keys via a JWKS should resolve the active key
+    /// to this field out of band (e.g. a sidecar that refreshes config).
+    pub public_key_pem: String,
+    pub issuer: String,
+    pub audience: String,
+    /// The name of the custom claim that lists which model names this
+    /// caller is permitted to invoke.
+    #[serde(default = "default_models_claim")]
+    pub allowed_models_claim: String,
+}
+
+fn default_models_claim() -> String {
+    "tensorzero_allowed_models".to_string()
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum JwtAlgorithm {
+    RS256,
+    ES256,
+}
+
+impl From<JwtAlgorithm> for Algorithm {
+    fn from(algorithm: JwtAlgorithm) -> Self {
+        match algorithm {
+            JwtAlgorithm::RS256 => Algorithm::RS256,
+            JwtAlgorithm::ES256 => Algorithm::ES256,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Claims {
+    sub: String,
+    #[serde(default)]
+    exp: Option<u64>,
+    #[serde(default)]
+    nbf: Option<u64>,
+    #[serde(flatten)]
+    extra: HashMap<String, serde_json::Value>,
+}
+
+/// The caller identity and permissions derived from a validated JWT, threaded
+/// through to inference/embedding dispatch and recorded alongside the
+/// ClickHouse inference row for auditing.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedIdentity {
+    pub subject: String,
+    pub allowed_models: Vec<String>,
+}
+
+impl AuthenticatedIdentity {
+    pub fn may_invoke(&self, model_name: &str) -> bool {
+        self.allowed_models.iter().any(|m| m == model_name)
+    }
+}
+
+/// Verifies `token`'s signature and standard claims (`exp`, `nbf`, `iss`,
+/// `aud`) against `config`, returning the resolved caller identity on
+/// success. Expired or audience-mismatched tokens are rejected before any
+/// provider call is attempted.
+pub fn verify_and_resolve_identity(
+    token: &str,
+    config: &JwtAuthConfig,
+) -> Result<AuthenticatedIdentity, Error> {
+    let decoding_key = match config.algorithm {
+        JwtAlgorithm::RS256 => DecodingKey::from_rsa_pem(config.public_key_pem.as_bytes()),
+        JwtAlgorithm::ES256 => DecodingKey::from_ec_pem(config.public_key_pem.as_bytes()),
+    }
+    .map_err(|e| {
+        Error::new(ErrorDetails::Config {
+            message: format!("Invalid JWT public key: {e}"),
+        })
+    })?;
+
+    let mut validation = Validation::new(config.algorithm.into());
+    validation.set_issuer(&[&config.issuer]);
+    validation.set_audience(&[&config.audience]);
+    validation.validate_nbf = true;
+    validation.leeway = 0;
+
+    let token_data = decode::<Claims>(token, &decoding_key, &validation).map_err(|e| {
+        Error::new(ErrorDetails::AuthenticationFailed {
+            message: format!("JWT validation failed: {e}"),
+        })
+    })?;
+    let claims = token_data.claims;
+
+    let allowed_models = claims
+        .extra
+        .get(&config.allowed_models_claim)
+        .and_then(|value| value.as_array())
+        .map(|values| {
+            values
+                .iter()
+                .filter_map(|v| v.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(AuthenticatedIdentity {
+        subject: claims.sub,
+        allowed_models,
+    })
+}
+
+/// Derives the set of provider API keys this caller may use from their
+/// validated claims, falling back to the gateway's statically configured
+/// credentials for any provider the token doesn't override. Returns
+/// `identity.subject` alongside the resolved credentials, since the caller
+/// needs it to record who made the request on the inference row for
+/// auditing; unlike the credentials, the subject isn't something the caller
+/// can reconstruct from `fallback`, so it can't be an afterthought here.
+pub fn resolve_credentials(
+    identity: &AuthenticatedIdentity,
+    claim_credentials: HashMap<String, String>,
+    fallback: &InferenceCredentials,
+) -> (InferenceCredentials, String) {
+    let mut resolved = fallback.clone();
+    for (provider, key) in claim_credentials {
+        resolved.insert(provider, key);
+    }
+    (resolved, identity.subject.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_may_invoke() {
+        let identity = AuthenticatedIdentity {
+            subject: "tenant-a".to_string(),
+            allowed_models: vec!["gpt-4o-mini".to_string()],
+        };
+        assert!(identity.may_invoke("gpt-4o-mini"));
+        assert!(!identity.may_invoke("gpt-4o"));
+    }
+
+    #[test]
+    fn test_verify_and_resolve_identity_rejects_garbage_key() {
+        let config = JwtAuthConfig {
+            algorithm: JwtAlgorithm::RS256,
+            public_key_pem: "not a real pem".to_string(),
+            issuer: "tensorzero".to_string(),
+            audience: "tensorzero-gateway".to_string(),
+            allowed_models_claim: default_models_claim(),
+        };
+        assert!(verify_and_resolve_identity("not.a.jwt", &config).is_err());
+    }
+
+    // Throwaway RSA keypair generated solely for these tests (not used
+    // anywhere outside this test module).
+    const TEST_PRIVATE_KEY_PEM: &str = include_str!("../testdata/jwt_test_rsa_private.pem");
+    const TEST_PUBLIC_KEY_PEM: &str = include_str!("../testdata/jwt_test_rsa_public.pem");
+
+    fn test_config() -> JwtAuthConfig {
+        JwtAuthConfig {
+            algorithm: JwtAlgorithm::RS256,
+            public_key_pem: TEST_PUBLIC_KEY_PEM.to_string(),
+            issuer: "tensorzero".to_string(),
+            audience: "tensorzero-gateway".to_string(),
+            allowed_models_claim: default_models_claim(),
+        }
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    fn mint_token(claims: serde_json::Value) -> String {
+        encode(
+            &Header::new(Algorithm::RS256),
+            &claims,
+            &EncodingKey::from_rsa_pem(TEST_PRIVATE_KEY_PEM.as_bytes()).unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_verify_and_resolve_identity_accepts_valid_token_and_maps_claims() {
+        let config = test_config();
+        let token = mint_token(json!({
+            "sub": "tenant-a",
+            "iss": config.issuer,
+            "aud": config.audience,
+            "exp": now() + 3600,
+            "tensorzero_allowed_models": ["gpt-4o-mini", "claude-haiku"],
+        }));
+
+        let identity = verify_and_resolve_identity(&token, &config).unwrap();
+        assert_eq!(identity.subject, "tenant-a");
+        assert!(identity.may_invoke("gpt-4o-mini"));
+        assert!(identity.may_invoke("claude-haiku"));
+        assert!(!identity.may_invoke("gpt-4o"));
+    }
+
+    #[test]
+    fn test_verify_and_resolve_identity_rejects_expired_token() {
+        let config = test_config();
+        let token = mint_token(json!({
+            "sub": "tenant-a",
+            "iss": config.issuer,
+            "aud": config.audience,
+            "exp": now() - 60,
+        }));
+
+        assert!(verify_and_resolve_identity(&token, &config).is_err());
+    }
+
+    #[test]
+    fn test_verify_and_resolve_identity_rejects_not_yet_valid_token() {
+        let config = test_config();
+        let token = mint_token(json!({
+            "sub": "tenant-a",
+            "iss": config.issuer,
+            "aud": config.audience,
+            "exp": now() + 3600,
+            "nbf": now() + 1800,
+        }));
+
+        assert!(verify_and_resolve_identity(&token, &config).is_err());
+    }
+
+    #[test]
+    fn test_verify_and_resolve_identity_rejects_wrong_audience() {
+        let config = test_config();
+        let token = mint_token(json!({
+            "sub": "tenant-a",
+            "iss": config.issuer,
+            "aud": "some-other-service",
+            "exp": now() + 3600,
+        }));
+
+        assert!(verify_and_resolve_identity(&token, &config).is_err());
+    }
+
+    #[test]
+    fn test_verify_and_resolve_identity_rejects_wrong_issuer() {
+        let config = test_config();
+        let token = mint_token(json!({
+            "sub": "tenant-a",
+            "iss": "not-tensorzero",
+            "aud": config.audience,
+            "exp": now() + 3600,
+        }));
+
+        assert!(verify_and_resolve_identity(&token, &config).is_err());
+    }
+
+    #[test]
+    fn test_resolve_credentials_returns_subject_for_auditing() {
+        let identity = AuthenticatedIdentity {
+            subject: "tenant-a".to_string(),
+            allowed_models: vec!["gpt-4o-mini".to_string()],
+        };
+        let claim_credentials =
+            HashMap::from([("openai_api_key".to_string(), "claim-key".to_string())]);
+        let fallback = InferenceCredentials::default();
+
+        let (resolved, subject) = resolve_credentials(&identity, claim_credentials, &fallback);
+        assert_eq!(subject, "tenant-a");
+        assert_eq!(resolved.get("openai_api_key"), Some(&"claim-key".to_string()));
+    }
+
+    #[test]
+    fn test_verify_and_resolve_identity_defaults_to_no_allowed_models() {
+        let config = test_config();
+        let token = mint_token(json!({
+            "sub": "tenant-a",
+            "iss": config.issuer,
+            "aud": config.audience,
+            "exp": now() + 3600,
+        }));
+
+        let identity = verify_and_resolve_identity(&token, &config).unwrap();
+        assert!(!identity.may_invoke("gpt-4o-mini"));
+    }
+}
@@ -1,4 +1,9 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::{broadcast, mpsc};
 
 use crate::clickhouse::ClickHouseConnectionInfo;
 use crate::error::{Error, ErrorDetails};
@@ -14,12 +19,70 @@ pub struct CacheOptions {
     pub write: bool,
     #[serde(default)]
     pub max_age_s: Option<u32>,
+    #[serde(default)]
+    pub fallback_mode: CacheFallbackMode,
 }
 
 fn default_write() -> bool {
     true
 }
 
+/// How the cache should degrade when ClickHouse is unhealthy, borrowed from
+/// the same recovery shape Deno's cache uses: fail closed, fail open into
+/// the process-local tier, or fail loud.
+///
+/// Defaults to `Error` so existing/unconfigured deployments keep today's
+/// fail-hard behavior; opting into `BlackHole` or `InMemory` degradation is
+/// an explicit choice.
+///
+/// Only `cache_lookup` consults the mode. The write path does not: in every
+/// mode `start_cache_write` fills the in-process LRU tier (when one is given),
+/// and rows whose ClickHouse flush fails are logged and dropped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheFallbackMode {
+    /// A failed ClickHouse lookup is returned as a miss (`Ok(None)`).
+    BlackHole,
+    /// A failed ClickHouse lookup is returned as a miss (`Ok(None)`), so the
+    /// in-process LRU tier, which `cache_lookup` checks first, is the only
+    /// source of hits while ClickHouse is down.
+    InMemory,
+    /// Preserve today's behavior: propagate the ClickHouse error.
+    #[default]
+    Error,
+}
+
+/// How many times to retry a transient ClickHouse connection error before
+/// invoking `CacheFallbackMode`. A single blip shouldn't trip the fallback.
+const CLICKHOUSE_RETRY_ATTEMPTS: u32 = 2;
+
+async fn run_query_with_retry(
+    clickhouse_connection_info: &ClickHouseConnectionInfo,
+    query: String,
+    query_params: &HashMap<&str, &str>,
+) -> Result<String, Error> {
+    let mut last_err = None;
+    for attempt in 0..=CLICKHOUSE_RETRY_ATTEMPTS {
+        match clickhouse_connection_info
+            .run_query(query.clone(), Some(query_params))
+            .await
+        {
+            Ok(result) => return Ok(result),
+            Err(e) => {
+                last_err = Some(e);
+                if attempt < CLICKHOUSE_RETRY_ATTEMPTS {
+                    continue;
+                }
+            }
+        }
+    }
+    Err(last_err.unwrap_or_else(|| {
+        Error::new(ErrorDetails::Cache {
+            message: "ClickHouse query failed with no recorded error".to_string(),
+        })
+    }))
+}
+
 #[derive(Debug, Clone)]
 pub struct ModelProviderRequest<'request> {
     pub request: &'request ModelInferenceRequest<'request>,
@@ -27,7 +90,7 @@ pub struct ModelProviderRequest<'request> {
     pub provider_name: &'request str,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct CacheKey([u8; 32]);
 
 impl CacheKey {
@@ -63,7 +126,108 @@ impl ModelProviderRequest<'_> {
     }
 }
 
-#[derive(Debug, Serialize)]
+/// Configuration for the in-process LRU tier in front of the ClickHouse
+/// cache. Kept on `AppStateData` so operators can tune memory use.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InMemoryCacheConfig {
+    #[serde(default = "default_max_entries")]
+    pub max_entries: usize,
+}
+
+fn default_max_entries() -> usize {
+    10_000
+}
+
+impl Default for InMemoryCacheConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: default_max_entries(),
+        }
+    }
+}
+
+struct InMemoryCacheEntry {
+    result: CacheLookupResult,
+    inserted_at: Instant,
+}
+
+/// An in-process LRU tier keyed by `CacheKey`, checked before a ClickHouse
+/// round trip in `cache_lookup` and populated on every write and every
+/// ClickHouse hit. Only ever durable for the lifetime of this process --
+/// ClickHouse remains the source of truth shared across replicas.
+pub struct InMemoryModelInferenceCache {
+    max_entries: usize,
+    entries: Mutex<HashMap<CacheKey, InMemoryCacheEntry>>,
+    // Recency order for eviction: the front is the least recently used key,
+    // the back the most recently used. `get` moves a hit to the back so a
+    // burst of cold insertions evicts cold keys first, not a hot one.
+    order: Mutex<VecDeque<CacheKey>>,
+}
+
+impl InMemoryModelInferenceCache {
+    pub fn new(config: &InMemoryCacheConfig) -> Self {
+        Self {
+            max_entries: config.max_entries,
+            entries: Mutex::new(HashMap::new()),
+            order: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    fn get(&self, key: &CacheKey, max_age_s: Option<u32>) -> Option<CacheLookupResult> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = entries.get(key)?;
+        if let Some(max_age_s) = max_age_s {
+            if entry.inserted_at.elapsed() > Duration::from_secs(max_age_s as u64) {
+                return None;
+            }
+        }
+        let result = CacheLookupResult {
+            output: entry.result.output.clone(),
+            raw_request: entry.result.raw_request.clone(),
+            raw_response: entry.result.raw_response.clone(),
+        };
+        drop(entries);
+
+        // Touch: move this key to the back of the eviction order so a hot
+        // key survives a burst of cold insertions instead of aging out in
+        // insertion order.
+        let mut order = self.order.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(pos) = order.iter().position(|k| k == key) {
+            order.remove(pos);
+            order.push_back(*key);
+        }
+
+        Some(result)
+    }
+
+    fn insert(&self, key: CacheKey, result: CacheLookupResult) {
+        if self.max_entries == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let mut order = self.order.lock().unwrap_or_else(|e| e.into_inner());
+        if !entries.contains_key(&key) {
+            order.push_back(key);
+        }
+        entries.insert(
+            key,
+            InMemoryCacheEntry {
+                result,
+                inserted_at: Instant::now(),
+            },
+        );
+        while entries.len() > self.max_entries {
+            if let Some(oldest) = order.pop_front() {
+                entries.remove(&oldest);
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 struct ModelInferenceCacheRow {
     short_cache_key: u64,
     long_cache_key: String,
@@ -72,13 +236,134 @@ struct ModelInferenceCacheRow {
     raw_response: String,
 }
 
-// This doesn't block
-pub fn start_cache_write(
+/// Tuning knobs for the background cache-write queue: how many rows may be
+/// buffered before `enqueue` starts dropping, how many rows go out in one
+/// `write`, and how often a partial batch gets flushed anyway.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CacheWriteQueueConfig {
+    #[serde(default = "default_channel_capacity")]
+    pub channel_capacity: usize,
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// Clamped to a minimum of 1ms when the queue is spawned --
+    /// `tokio::time::interval` panics on a zero-duration period, and `0`
+    /// is a plausible typo for "flush immediately".
+    #[serde(default = "default_flush_interval_ms")]
+    pub flush_interval_ms: u64,
+}
+
+fn default_channel_capacity() -> usize {
+    10_000
+}
+
+fn default_batch_size() -> usize {
+    100
+}
+
+fn default_flush_interval_ms() -> u64 {
+    100
+}
+
+impl Default for CacheWriteQueueConfig {
+    fn default() -> Self {
+        Self {
+            channel_capacity: default_channel_capacity(),
+            batch_size: default_batch_size(),
+            flush_interval_ms: default_flush_interval_ms(),
+        }
+    }
+}
+
+/// A single long-lived background task that owns an `mpsc` channel of rows
+/// destined for `ModelInferenceCache`, flushing them to ClickHouse in
+/// batches instead of spawning (and issuing a single-row insert for) a new
+/// task on every request.
+pub struct CacheWriteQueue {
+    sender: mpsc::Sender<ModelInferenceCacheRow>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl CacheWriteQueue {
+    /// Spawns the background writer task. Dropping the returned
+    /// `CacheWriteQueue` closes the channel, which causes the
+    /// writer to flush whatever it's holding and exit -- a graceful
+    /// shutdown flush falls naturally out of that.
+    pub fn spawn(
+        clickhouse_client: ClickHouseConnectionInfo,
+        config: CacheWriteQueueConfig,
+    ) -> Self {
+        let (sender, mut receiver) = mpsc::channel(config.channel_capacity);
+        let dropped = Arc::new(AtomicU64::new(0));
+        // tokio::time::interval panics on a zero-duration period; clamp
+        // rather than letting a misconfigured `0` take down the writer task.
+        let flush_interval_ms = config.flush_interval_ms.max(1);
+        tokio::spawn(async move {
+            let mut batch = Vec::with_capacity(config.batch_size);
+            let mut interval = tokio::time::interval(Duration::from_millis(flush_interval_ms));
+            loop {
+                tokio::select! {
+                    maybe_row = receiver.recv() => {
+                        match maybe_row {
+                            Some(row) => {
+                                batch.push(row);
+                                if batch.len() >= config.batch_size {
+                                    flush_batch(&clickhouse_client, &mut batch).await;
+                                }
+                            }
+                            None => {
+                                flush_batch(&clickhouse_client, &mut batch).await;
+                                return;
+                            }
+                        }
+                    }
+                    _ = interval.tick() => {
+                        flush_batch(&clickhouse_client, &mut batch).await;
+                    }
+                }
+            }
+        });
+        Self { sender, dropped }
+    }
+
+    /// Non-blocking: drops (and counts) the row instead of blocking the
+    /// caller when the queue is full, so a slow ClickHouse never stalls
+    /// inference.
+    fn enqueue(&self, row: ModelInferenceCacheRow) {
+        if self.sender.try_send(row).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn dropped_count(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+async fn flush_batch(
     clickhouse_client: &ClickHouseConnectionInfo,
+    batch: &mut Vec<ModelInferenceCacheRow>,
+) {
+    if batch.is_empty() {
+        return;
+    }
+    let rows = std::mem::take(batch);
+    if let Err(e) = clickhouse_client.write(&rows, "ModelInferenceCache").await {
+        tracing::error!("Failed to flush {} rows to ModelInferenceCache: {e}", rows.len());
+    }
+}
+
+// This doesn't block: the row is handed to the background write queue
+// (or dropped under backpressure -- see `CacheWriteQueue::enqueue`).
+// The in-memory tier, if configured, is populated unconditionally so it
+// stays warm regardless of whether ClickHouse is currently healthy.
+pub fn start_cache_write(
+    cache_write_queue: &CacheWriteQueue,
     request: ModelProviderRequest<'_>,
     output: &[ContentBlock],
     raw_request: &str,
     raw_response: &str,
+    memory_cache: Option<&InMemoryModelInferenceCache>,
 ) -> Result<(), Error> {
     let cache_key = request.get_cache_key()?;
     let short_cache_key = cache_key.get_short_key()?;
@@ -86,20 +371,24 @@ pub fn start_cache_write(
     let output = output.to_owned();
     let raw_request = raw_request.to_string();
     let raw_response = raw_response.to_string();
-    let clickhouse_client = clickhouse_client.clone();
-    tokio::spawn(async move {
-        clickhouse_client
-            .write(
-                &[ModelInferenceCacheRow {
-                    short_cache_key,
-                    long_cache_key,
-                    output,
-                    raw_request,
-                    raw_response,
-                }],
-                "ModelInferenceCache",
-            )
-            .await
+
+    if let Some(memory_cache) = memory_cache {
+        memory_cache.insert(
+            cache_key,
+            CacheLookupResult {
+                output: output.clone(),
+                raw_request: raw_request.clone(),
+                raw_response: raw_response.clone(),
+            },
+        );
+    }
+
+    cache_write_queue.enqueue(ModelInferenceCacheRow {
+        short_cache_key,
+        long_cache_key,
+        output,
+        raw_request,
+        raw_response,
     });
     Ok(())
 }
@@ -116,8 +405,21 @@ pub async fn cache_lookup(
     clickhouse_connection_info: &ClickHouseConnectionInfo,
     request: ModelProviderRequest<'_>,
     max_age_s: Option<u32>,
+    memory_cache: Option<&InMemoryModelInferenceCache>,
+    fallback_mode: CacheFallbackMode,
 ) -> Result<Option<ModelInferenceResponse>, Error> {
     let cache_key = request.get_cache_key()?;
+
+    if let Some(memory_cache) = memory_cache {
+        if let Some(result) = memory_cache.get(&cache_key, max_age_s) {
+            return Ok(Some(ModelInferenceResponse::from_cache(
+                result,
+                request.request,
+                request.provider_name,
+            )));
+        }
+    }
+
     // NOTE: the short cache key is just so the ClickHouse index can be as efficient as possible
     // but we always check against the long cache key before returning a result
     let short_cache_key = cache_key.get_short_key()?.to_string();
@@ -159,9 +461,18 @@ pub async fn cache_lookup(
         lookback_str = lookback.to_string();
         query_params.insert("lookback_s", lookback_str.as_str());
     }
-    let result = clickhouse_connection_info
-        .run_query(query.to_string(), Some(&query_params))
-        .await?;
+    let result = match run_query_with_retry(clickhouse_connection_info, query.to_string(), &query_params)
+        .await
+    {
+        Ok(result) => result,
+        Err(e) => {
+            return match fallback_mode {
+                CacheFallbackMode::BlackHole => Ok(None),
+                CacheFallbackMode::InMemory => Ok(None),
+                CacheFallbackMode::Error => Err(e),
+            };
+        }
+    };
     if result.is_empty() {
         return Ok(None);
     }
@@ -170,6 +481,16 @@ pub async fn cache_lookup(
             message: format!("Failed to deserialize output: {e}"),
         })
     })?;
+    if let Some(memory_cache) = memory_cache {
+        memory_cache.insert(
+            cache_key,
+            CacheLookupResult {
+                output: result.output.clone(),
+                raw_request: result.raw_request.clone(),
+                raw_response: result.raw_response.clone(),
+            },
+        );
+    }
     Ok(Some(ModelInferenceResponse::from_cache(
         result,
         request.request,
@@ -177,6 +498,128 @@ pub async fn cache_lookup(
     )))
 }
 
+/// Single-flight request coalescing for cache misses: when several identical
+/// requests (same `CacheKey`) arrive concurrently, only the first one
+/// ("leader") actually calls the provider; the rest ("waiters") await its
+/// result instead of each issuing their own duplicate, expensive call.
+pub struct RequestCoalescer {
+    in_flight: Mutex<HashMap<CacheKey, broadcast::Sender<Result<ModelInferenceResponse, String>>>>,
+}
+
+impl Default for RequestCoalescer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Removes this key's in-flight slot on drop unless it was already removed,
+/// so a panicking or cancelled leader can't leave waiters stuck forever.
+/// Only removes the slot if it still holds the sender we registered --
+/// otherwise a finisher could rip out a different, still-running leader's
+/// slot (e.g. one that registered for the same key after a prior leader's
+/// slot was already cleaned up).
+struct InFlightGuard<'a> {
+    coalescer: &'a RequestCoalescer,
+    key: CacheKey,
+    sender: broadcast::Sender<Result<ModelInferenceResponse, String>>,
+    completed: bool,
+}
+
+impl InFlightGuard<'_> {
+    fn remove_owned_slot(&self) {
+        let mut in_flight = self
+            .coalescer
+            .in_flight
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        if in_flight
+            .get(&self.key)
+            .is_some_and(|current| current.same_channel(&self.sender))
+        {
+            in_flight.remove(&self.key);
+        }
+    }
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        if !self.completed {
+            self.remove_owned_slot();
+        }
+    }
+}
+
+impl RequestCoalescer {
+    pub fn new() -> Self {
+        Self {
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Runs `attempt` to produce a `ModelInferenceResponse` for `key`,
+    /// coalescing concurrent callers with the same key onto a single
+    /// in-flight call. Does not look at `CacheOptions` itself -- if
+    /// caching is disabled, callers should skip this and call `attempt`
+    /// directly instead.
+    pub async fn dispatch<F, Fut>(&self, key: CacheKey, attempt: F) -> Result<ModelInferenceResponse, Error>
+    where
+        F: Fn() -> Fut,
+        Fut: std::future::Future<Output = Result<ModelInferenceResponse, Error>>,
+    {
+        // Atomically check-and-insert under a single lock: two concurrent
+        // callers for the same key must never both observe an empty slot
+        // and both become leaders, so the existing-sender lookup and the
+        // leader's registration happen in one critical section via `entry`.
+        let registration = {
+            let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+            match in_flight.entry(key) {
+                std::collections::hash_map::Entry::Occupied(occupied) => {
+                    Err(occupied.get().subscribe())
+                }
+                std::collections::hash_map::Entry::Vacant(vacant) => {
+                    let (sender, _receiver) = broadcast::channel(1);
+                    vacant.insert(sender.clone());
+                    Ok(sender)
+                }
+            }
+        };
+
+        let sender = match registration {
+            Err(mut receiver) => {
+                return match receiver.recv().await {
+                    Ok(Ok(response)) => Ok(response),
+                    // The leader failed or the channel was dropped (e.g. the
+                    // leader panicked); fall back to issuing our own request
+                    // rather than propagating someone else's failure.
+                    Ok(Err(_)) | Err(_) => attempt().await,
+                };
+            }
+            Ok(sender) => sender,
+        };
+
+        // No one else was in flight for this key: we're the leader.
+        let mut guard = InFlightGuard {
+            coalescer: self,
+            key,
+            sender: sender.clone(),
+            completed: false,
+        };
+
+        let result = attempt().await;
+        guard.remove_owned_slot();
+        guard.completed = true;
+        let broadcastable = result
+            .as_ref()
+            .map(Clone::clone)
+            .map_err(|e| e.to_string());
+        // No receivers is not an error here -- every waiter that arrived
+        // before we finished already got a clone of the `Receiver` above;
+        // it's fine if nobody happened to be waiting.
+        let _ = sender.send(broadcastable);
+        result
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -184,6 +627,21 @@ mod tests {
 
     use super::*;
 
+    /// A mock `ClickHouseConnectionInfo` whose queries/writes always
+    /// succeed trivially, for tests that exercise code paths around a
+    /// ClickHouse call without caring about its result.
+    fn healthy_clickhouse() -> ClickHouseConnectionInfo {
+        ClickHouseConnectionInfo::new("", true, Some(true))
+            .expect("constructing a mock ClickHouseConnectionInfo should not fail")
+    }
+
+    /// A mock `ClickHouseConnectionInfo` whose queries/writes always fail,
+    /// simulating ClickHouse being down, for testing fallback/retry paths.
+    fn unhealthy_clickhouse() -> ClickHouseConnectionInfo {
+        ClickHouseConnectionInfo::new("", true, Some(false))
+            .expect("constructing a mock ClickHouseConnectionInfo should not fail")
+    }
+
     /// This test ensures that if we make a small change to the ModelInferenceRequest,
     /// the cache key will change.
     #[test]
@@ -232,4 +690,275 @@ mod tests {
         let streaming_cache_key = model_provider_request.get_cache_key().unwrap();
         assert_ne!(cache_key, streaming_cache_key);
     }
+
+    fn dummy_cache_key(seed: u8) -> CacheKey {
+        CacheKey([seed; 32])
+    }
+
+    fn dummy_result(raw_request: &str) -> CacheLookupResult {
+        CacheLookupResult {
+            output: vec![],
+            raw_request: raw_request.to_string(),
+            raw_response: "{}".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_in_memory_cache_hit() {
+        let cache = InMemoryModelInferenceCache::new(&InMemoryCacheConfig { max_entries: 10 });
+        let key = dummy_cache_key(1);
+        cache.insert(key, dummy_result("request a"));
+        let hit = cache.get(&key, None).unwrap();
+        assert_eq!(hit.raw_request, "request a");
+    }
+
+    #[test]
+    fn test_in_memory_cache_respects_max_age() {
+        let cache = InMemoryModelInferenceCache::new(&InMemoryCacheConfig { max_entries: 10 });
+        let key = dummy_cache_key(2);
+        cache.insert(key, dummy_result("request b"));
+        // Not expired with no max age, or a generous one.
+        assert!(cache.get(&key, None).is_some());
+        assert!(cache.get(&key, Some(3600)).is_some());
+        // Immediately "expired" against a max age of 0 seconds.
+        assert!(cache.get(&key, Some(0)).is_none());
+    }
+
+    #[test]
+    fn test_in_memory_cache_evicts_oldest_when_full() {
+        let cache = InMemoryModelInferenceCache::new(&InMemoryCacheConfig { max_entries: 2 });
+        let key1 = dummy_cache_key(1);
+        let key2 = dummy_cache_key(2);
+        let key3 = dummy_cache_key(3);
+        cache.insert(key1, dummy_result("a"));
+        cache.insert(key2, dummy_result("b"));
+        cache.insert(key3, dummy_result("c"));
+        assert!(cache.get(&key1, None).is_none());
+        assert!(cache.get(&key2, None).is_some());
+        assert!(cache.get(&key3, None).is_some());
+    }
+
+    #[test]
+    fn test_in_memory_cache_hit_protects_key_from_eviction() {
+        let cache = InMemoryModelInferenceCache::new(&InMemoryCacheConfig { max_entries: 2 });
+        let key1 = dummy_cache_key(1);
+        let key2 = dummy_cache_key(2);
+        let key3 = dummy_cache_key(3);
+        cache.insert(key1, dummy_result("a"));
+        cache.insert(key2, dummy_result("b"));
+        // Touch key1 so it becomes the most recently used entry.
+        assert!(cache.get(&key1, None).is_some());
+        // A burst of cold insertions should now evict key2 (least recently
+        // used), not the hot key1, which plain insertion-order FIFO would
+        // have gotten wrong.
+        cache.insert(key3, dummy_result("c"));
+        assert!(cache.get(&key1, None).is_some());
+        assert!(cache.get(&key2, None).is_none());
+        assert!(cache.get(&key3, None).is_some());
+    }
+
+    fn dummy_model_inference_request() -> ModelInferenceRequest<'static> {
+        ModelInferenceRequest {
+            messages: vec![],
+            system: None,
+            tool_config: None,
+            temperature: None,
+            top_p: None,
+            presence_penalty: None,
+            frequency_penalty: None,
+            max_tokens: None,
+            seed: None,
+            stream: false,
+            json_mode: ModelInferenceRequestJsonMode::Off,
+            function_type: FunctionType::Chat,
+            output_schema: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_request_coalescer_deduplicates_concurrent_callers() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        let coalescer = RequestCoalescer::new();
+        let attempt_count = AtomicU32::new(0);
+        let key = dummy_cache_key(7);
+        let request = dummy_model_inference_request();
+
+        let attempt = || async {
+            attempt_count.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            Ok(ModelInferenceResponse::from_cache(
+                dummy_result("leader request"),
+                &request,
+                "test_provider",
+            ))
+        };
+
+        let (a, b) = tokio::join!(
+            coalescer.dispatch(key, attempt),
+            coalescer.dispatch(key, attempt)
+        );
+        assert!(a.is_ok());
+        assert!(b.is_ok());
+        assert_eq!(attempt_count.load(Ordering::SeqCst), 1);
+    }
+
+    /// Uses a real multi-threaded runtime and `tokio::spawn` (rather than
+    /// `tokio::join!` on a single task) so registration actually races
+    /// across OS threads instead of being cooperatively serialized before
+    /// the first `.await` -- this is what would have caught the
+    /// check-then-insert TOCTOU in the original implementation.
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_request_coalescer_deduplicates_under_real_concurrency() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        let coalescer = Arc::new(RequestCoalescer::new());
+        let attempt_count = Arc::new(AtomicU32::new(0));
+        let key = dummy_cache_key(8);
+        let request = Arc::new(dummy_model_inference_request());
+
+        let mut handles = Vec::new();
+        for _ in 0..8 {
+            let coalescer = Arc::clone(&coalescer);
+            let attempt_count = Arc::clone(&attempt_count);
+            let request = Arc::clone(&request);
+            handles.push(tokio::spawn(async move {
+                let attempt = {
+                    let attempt_count = Arc::clone(&attempt_count);
+                    let request = Arc::clone(&request);
+                    move || {
+                        let attempt_count = Arc::clone(&attempt_count);
+                        let request = Arc::clone(&request);
+                        async move {
+                            attempt_count.fetch_add(1, Ordering::SeqCst);
+                            tokio::time::sleep(Duration::from_millis(20)).await;
+                            Ok(ModelInferenceResponse::from_cache(
+                                dummy_result("leader request"),
+                                &request,
+                                "test_provider",
+                            ))
+                        }
+                    }
+                };
+                coalescer.dispatch(key, attempt).await
+            }));
+        }
+
+        for handle in handles {
+            assert!(handle.await.unwrap().is_ok());
+        }
+        assert_eq!(attempt_count.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_cache_write_queue_config_defaults() {
+        let config = CacheWriteQueueConfig::default();
+        assert_eq!(config.channel_capacity, 10_000);
+        assert_eq!(config.batch_size, 100);
+        assert_eq!(config.flush_interval_ms, 100);
+    }
+
+    #[test]
+    fn test_cache_fallback_mode_defaults_to_error() {
+        // Unconfigured deployments must keep today's fail-hard behavior, not
+        // silently start swallowing ClickHouse failures.
+        assert_eq!(CacheFallbackMode::default(), CacheFallbackMode::Error);
+    }
+
+    fn dummy_model_provider_request(
+        request: &ModelInferenceRequest<'_>,
+    ) -> ModelProviderRequest<'_> {
+        ModelProviderRequest {
+            request,
+            model_name: "test_model",
+            provider_name: "test_provider",
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_query_with_retry_fails_after_exhausting_attempts() {
+        let clickhouse = unhealthy_clickhouse();
+        let result = run_query_with_retry(
+            &clickhouse,
+            "SELECT 1 FORMAT JSONEachRow".to_string(),
+            &HashMap::new(),
+        )
+        .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cache_lookup_blackhole_fallback_swallows_clickhouse_error() {
+        let clickhouse = unhealthy_clickhouse();
+        let request = dummy_model_inference_request();
+        let result = cache_lookup(
+            &clickhouse,
+            dummy_model_provider_request(&request),
+            None,
+            None,
+            CacheFallbackMode::BlackHole,
+        )
+        .await;
+        assert!(matches!(result, Ok(None)));
+    }
+
+    #[tokio::test]
+    async fn test_cache_lookup_error_fallback_propagates_clickhouse_error() {
+        let clickhouse = unhealthy_clickhouse();
+        let request = dummy_model_inference_request();
+        let result = cache_lookup(
+            &clickhouse,
+            dummy_model_provider_request(&request),
+            None,
+            None,
+            CacheFallbackMode::Error,
+        )
+        .await;
+        assert!(result.is_err());
+    }
+
+    fn dummy_cache_row(seed: u8) -> ModelInferenceCacheRow {
+        ModelInferenceCacheRow {
+            short_cache_key: seed as u64,
+            long_cache_key: format!("key-{seed}"),
+            output: vec![],
+            raw_request: "request".to_string(),
+            raw_response: "response".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cache_write_queue_drops_and_counts_under_backpressure() {
+        let queue = CacheWriteQueue::spawn(
+            healthy_clickhouse(),
+            CacheWriteQueueConfig {
+                channel_capacity: 1,
+                batch_size: 1_000,
+                flush_interval_ms: 60_000,
+            },
+        );
+        // Enqueue synchronously, before yielding to the runtime, so the
+        // background task has had no chance to drain anything yet -- this
+        // is what makes the channel actually fill up and start dropping.
+        for seed in 0..5u8 {
+            queue.enqueue(dummy_cache_row(seed));
+        }
+        assert!(queue.dropped_count() >= 1);
+    }
+
+    #[tokio::test]
+    async fn test_cache_write_queue_clamps_zero_flush_interval() {
+        // A configured flush_interval_ms of 0 would otherwise panic inside
+        // tokio::time::interval; spawning must not panic.
+        let queue = CacheWriteQueue::spawn(
+            healthy_clickhouse(),
+            CacheWriteQueueConfig {
+                channel_capacity: 10,
+                batch_size: 10,
+                flush_interval_ms: 0,
+            },
+        );
+        queue.enqueue(dummy_cache_row(0));
+        assert_eq!(queue.dropped_count(), 0);
+    }
 }
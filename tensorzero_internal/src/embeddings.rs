@@ -0,0 +1,607 @@
+use std::time::Instant;
+
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::endpoints::inference::InferenceCredentials;
+use crate::error::{Error, ErrorDetails};
+use crate::inference::types::{Latency, Usage};
+use crate::provider_client_config::ProviderClientConfig;
+use crate::retry::RetryPolicy;
+
+/// One or more strings to embed in a single round trip. A bare string
+/// deserializes as a single-input request; an array is a batch request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum EmbeddingInput {
+    Single(String),
+    Batch(Vec<String>),
+}
+
+impl EmbeddingInput {
+    pub fn as_slice(&self) -> &[String] {
+        match self {
+            EmbeddingInput::Single(s) => std::slice::from_ref(s),
+            EmbeddingInput::Batch(inputs) => inputs,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            EmbeddingInput::Single(_) => 1,
+            EmbeddingInput::Batch(inputs) => inputs.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl From<String> for EmbeddingInput {
+    fn from(input: String) -> Self {
+        EmbeddingInput::Single(input)
+    }
+}
+
+impl From<Vec<String>> for EmbeddingInput {
+    fn from(inputs: Vec<String>) -> Self {
+        EmbeddingInput::Batch(inputs)
+    }
+}
+
+/// Default cap on how many inputs a single client-submitted embedding
+/// request may contain; oversized batches are rejected with a clear error
+/// rather than silently truncated or forwarded as-is to the provider.
+pub const DEFAULT_MAX_CLIENT_BATCH_SIZE: usize = 4;
+
+pub fn validate_batch_size(input: &EmbeddingInput, max_client_batch_size: usize) -> Result<(), Error> {
+    if input.len() > max_client_batch_size {
+        return Err(Error::new(ErrorDetails::InvalidRequest {
+            message: format!(
+                "Embedding request contains {} inputs, which exceeds the configured max_client_batch_size of {max_client_batch_size}",
+                input.len()
+            ),
+        }));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingRequest {
+    pub input: EmbeddingInput,
+}
+
+/// A single embedding vector, always unit-normalized, in the order its
+/// source input was given in the request.
+#[derive(Debug, Clone)]
+pub struct Embedding {
+    pub vector: Vec<f32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EmbeddingResponse {
+    /// Aligned 1:1 with the inputs in the originating `EmbeddingRequest`.
+    pub embeddings: Vec<Embedding>,
+    pub created: u64,
+    pub raw_request: String,
+    pub raw_response: String,
+    pub usage: Usage,
+    pub latency: Latency,
+}
+
+pub trait EmbeddingProvider {
+    fn embed(
+        &self,
+        request: &EmbeddingRequest,
+        client: &Client,
+        api_keys: &InferenceCredentials,
+    ) -> impl std::future::Future<Output = Result<EmbeddingResponse, Error>> + Send;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[serde(rename_all = "lowercase")]
+pub enum EmbeddingProviderConfig {
+    #[serde(rename = "openai")]
+    OpenAI(OpenAIEmbeddingProvider),
+    #[serde(rename = "ollama")]
+    Ollama(OllamaEmbeddingProvider),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenAIEmbeddingProvider {
+    pub model_name: String,
+    #[serde(default)]
+    pub api_base: Option<String>,
+    #[serde(default)]
+    pub client_config: ProviderClientConfig,
+    /// Retried the same way `ModelProviderRing::dispatch_with_retry` retries
+    /// a chat provider. Every failure is currently treated as retryable:
+    /// unlike `ProviderAttemptError`, nothing here yet carries the response's
+    /// HTTP status code for `RetryPolicy::is_retryable_status` to classify.
+    #[serde(default)]
+    pub retry_policy: RetryPolicy,
+}
+
+impl OpenAIEmbeddingProvider {
+    const DEFAULT_API_BASE: &'static str = "https://api.openai.com/v1";
+
+    fn api_base(&self) -> &str {
+        self.api_base.as_deref().unwrap_or(Self::DEFAULT_API_BASE)
+    }
+}
+
+impl EmbeddingProvider for OpenAIEmbeddingProvider {
+    async fn embed(
+        &self,
+        request: &EmbeddingRequest,
+        client: &Client,
+        api_keys: &InferenceCredentials,
+    ) -> Result<EmbeddingResponse, Error> {
+        self.retry_policy
+            .retry(
+                |_: &Error| true,
+                || self.embed_once(request, client, api_keys),
+            )
+            .await
+    }
+}
+
+impl OpenAIEmbeddingProvider {
+    async fn embed_once(
+        &self,
+        request: &EmbeddingRequest,
+        client: &Client,
+        api_keys: &InferenceCredentials,
+    ) -> Result<EmbeddingResponse, Error> {
+        // NOTE: `client` is the gateway-wide default client. Providers that need
+        // proxy/timeout/TLS overrides build their own via `self.client_config`
+        // and use it in place of `client` below.
+        let provider_client = self.client_config.effective_client(client)?;
+
+        let api_key = api_keys
+            .get("openai_api_key")
+            .cloned()
+            .or_else(|| std::env::var("OPENAI_API_KEY").ok())
+            .ok_or_else(|| {
+                Error::new(ErrorDetails::ApiKeyMissing {
+                    provider_name: "OpenAI".to_string(),
+                })
+            })?;
+
+        let raw_request = serde_json::to_string(&serde_json::json!({
+            "model": self.model_name,
+            // OpenAI's `/embeddings` endpoint accepts either a single string
+            // or an array in the `input` field, so the whole batch is sent
+            // in one HTTP call regardless of how many inputs were requested.
+            "input": request.input.as_slice(),
+        }))
+        .map_err(|e| {
+            Error::new(ErrorDetails::Serialization {
+                message: format!("Failed to serialize embedding request: {e}"),
+            })
+        })?;
+
+        let start = Instant::now();
+        let res = provider_client
+            .post(format!("{}/embeddings", self.api_base()))
+            .bearer_auth(api_key)
+            .header("Content-Type", "application/json")
+            .body(raw_request.clone())
+            .send()
+            .await
+            .map_err(|e| {
+                Error::new(ErrorDetails::InferenceClient {
+                    message: format!("Error sending request to OpenAI embeddings: {e}"),
+                })
+            })?;
+        let response_time = start.elapsed();
+
+        let raw_response = res.text().await.map_err(|e| {
+            Error::new(ErrorDetails::InferenceClient {
+                message: format!("Error reading OpenAI embeddings response: {e}"),
+            })
+        })?;
+
+        let parsed: OpenAIEmbeddingResponse = serde_json::from_str(&raw_response).map_err(|e| {
+            Error::new(ErrorDetails::InferenceClient {
+                message: format!("Error parsing OpenAI embeddings response: {e}"),
+            })
+        })?;
+
+        if parsed.data.len() != request.input.len() {
+            return Err(Error::new(ErrorDetails::InferenceClient {
+                message: format!(
+                    "OpenAI embeddings response returned {} embeddings for {} inputs",
+                    parsed.data.len(),
+                    request.input.len()
+                ),
+            }));
+        }
+        // The API returns embeddings tagged with their position in the
+        // batch; sort by that index so the result stays aligned with the
+        // request even if the provider reorders them.
+        let mut data = parsed.data;
+        data.sort_by_key(|d| d.index);
+        let embeddings = data
+            .into_iter()
+            .map(|d| Embedding {
+                vector: normalize(d.embedding),
+            })
+            .collect();
+
+        Ok(EmbeddingResponse {
+            embeddings,
+            created: parsed.created,
+            raw_request,
+            raw_response,
+            usage: Usage {
+                input_tokens: parsed.usage.prompt_tokens,
+                output_tokens: 0,
+            },
+            latency: Latency::NonStreaming { response_time },
+        })
+    }
+}
+
+impl EmbeddingProviderConfig {
+    pub async fn embed(
+        &self,
+        request: &EmbeddingRequest,
+        client: &Client,
+        api_keys: &InferenceCredentials,
+    ) -> Result<EmbeddingResponse, Error> {
+        match self {
+            EmbeddingProviderConfig::OpenAI(provider) => {
+                provider.embed(request, client, api_keys).await
+            }
+            EmbeddingProviderConfig::Ollama(provider) => {
+                provider.embed(request, client, api_keys).await
+            }
+        }
+    }
+}
+
+/// The entry point a gateway-facing `/embeddings` handler should call instead
+/// of [`EmbeddingProviderConfig::embed`] directly: rejects a client-submitted
+/// batch over `max_client_batch_size` before it ever reaches the provider.
+/// Internal callers that construct their own batches out of already-chunked
+/// text (e.g. [`crate::retrieval::index_document`]) call
+/// `EmbeddingProviderConfig::embed` directly instead, since their batch size
+/// is governed by `max_chunk_chars`, not this client-facing cap.
+pub async fn embed_client_request(
+    provider: &EmbeddingProviderConfig,
+    request: &EmbeddingRequest,
+    client: &Client,
+    api_keys: &InferenceCredentials,
+    max_client_batch_size: usize,
+) -> Result<EmbeddingResponse, Error> {
+    validate_batch_size(&request.input, max_client_batch_size)?;
+    provider.embed(request, client, api_keys).await
+}
+
+/// A local/self-hosted embedding provider speaking Ollama's native
+/// `/api/embed` request and response shape (a top-level `embeddings` array).
+/// It does not use Ollama's OpenAI-compatible `/v1/embeddings` endpoint. No
+/// API key is required since Ollama serves over a local/private `base_url`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaEmbeddingProvider {
+    pub model_name: String,
+    #[serde(default = "OllamaEmbeddingProvider::default_base_url")]
+    pub base_url: String,
+    #[serde(default)]
+    pub client_config: ProviderClientConfig,
+    /// See the doc comment on `OpenAIEmbeddingProvider::retry_policy`.
+    #[serde(default)]
+    pub retry_policy: RetryPolicy,
+}
+
+impl OllamaEmbeddingProvider {
+    fn default_base_url() -> String {
+        "http://localhost:11434".to_string()
+    }
+}
+
+impl EmbeddingProvider for OllamaEmbeddingProvider {
+    async fn embed(
+        &self,
+        request: &EmbeddingRequest,
+        client: &Client,
+        api_keys: &InferenceCredentials,
+    ) -> Result<EmbeddingResponse, Error> {
+        self.retry_policy
+            .retry(
+                |_: &Error| true,
+                || self.embed_once(request, client, api_keys),
+            )
+            .await
+    }
+}
+
+impl OllamaEmbeddingProvider {
+    async fn embed_once(
+        &self,
+        request: &EmbeddingRequest,
+        client: &Client,
+        _api_keys: &InferenceCredentials,
+    ) -> Result<EmbeddingResponse, Error> {
+        let provider_client = self.client_config.effective_client(client)?;
+
+        let raw_request = serde_json::to_string(&serde_json::json!({
+            "model": self.model_name,
+            "input": request.input.as_slice(),
+        }))
+        .map_err(|e| {
+            Error::new(ErrorDetails::Serialization {
+                message: format!("Failed to serialize embedding request: {e}"),
+            })
+        })?;
+
+        let start = Instant::now();
+        let res = provider_client
+            .post(format!("{}/api/embed", self.base_url))
+            .header("Content-Type", "application/json")
+            .body(raw_request.clone())
+            .send()
+            .await
+            .map_err(|e| {
+                Error::new(ErrorDetails::InferenceClient {
+                    message: format!("Error sending request to Ollama embeddings: {e}"),
+                })
+            })?;
+        let response_time = start.elapsed();
+
+        let raw_response = res.text().await.map_err(|e| {
+            Error::new(ErrorDetails::InferenceClient {
+                message: format!("Error reading Ollama embeddings response: {e}"),
+            })
+        })?;
+
+        let parsed: OllamaEmbeddingResponse = serde_json::from_str(&raw_response).map_err(|e| {
+            Error::new(ErrorDetails::InferenceClient {
+                message: format!("Error parsing Ollama embeddings response: {e}"),
+            })
+        })?;
+
+        if parsed.embeddings.len() != request.input.len() {
+            return Err(Error::new(ErrorDetails::InferenceClient {
+                message: format!(
+                    "Ollama embeddings response returned {} embeddings for {} inputs",
+                    parsed.embeddings.len(),
+                    request.input.len()
+                ),
+            }));
+        }
+
+        let embeddings = parsed
+            .embeddings
+            .into_iter()
+            .map(|vector| Embedding {
+                vector: normalize(vector),
+            })
+            .collect();
+
+        Ok(EmbeddingResponse {
+            embeddings,
+            created: default_created(),
+            raw_request,
+            raw_response,
+            usage: Usage {
+                input_tokens: parsed.prompt_eval_count.unwrap_or(0),
+                output_tokens: 0,
+            },
+            latency: Latency::NonStreaming { response_time },
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaEmbeddingResponse {
+    embeddings: Vec<Vec<f32>>,
+    #[serde(default)]
+    prompt_eval_count: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIEmbeddingResponse {
+    data: Vec<OpenAIEmbeddingData>,
+    usage: OpenAIEmbeddingUsage,
+    #[serde(default = "default_created")]
+    created: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIEmbeddingData {
+    embedding: Vec<f32>,
+    index: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIEmbeddingUsage {
+    prompt_tokens: u32,
+}
+
+fn default_created() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Normalizes an embedding to unit (L2) length so downstream consumers (e.g.
+/// cosine-similarity search) can compare vectors with a plain dot product.
+fn normalize(embedding: Vec<f32>) -> Vec<f32> {
+    let norm: f32 = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        return embedding;
+    }
+    embedding.into_iter().map(|x| x / norm).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_openai_embedding_provider_config() {
+        let serialized = r#"
+        type = "openai"
+        model_name = "text-embedding-3-small"
+        "#;
+        let config: EmbeddingProviderConfig = toml::from_str(serialized).unwrap();
+        match config {
+            EmbeddingProviderConfig::OpenAI(provider) => {
+                assert_eq!(provider.model_name, "text-embedding-3-small");
+                assert!(provider.client_config.proxy.is_none());
+            }
+            EmbeddingProviderConfig::Ollama(_) => panic!("expected OpenAI variant"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_openai_embedding_provider_config_with_proxy() {
+        let serialized = r#"
+        type = "openai"
+        model_name = "text-embedding-3-small"
+
+        [client_config]
+        proxy = "http://localhost:8080"
+        connect_timeout_ms = 1000
+        "#;
+        let config: EmbeddingProviderConfig = toml::from_str(serialized).unwrap();
+        match config {
+            EmbeddingProviderConfig::OpenAI(provider) => {
+                assert_eq!(
+                    provider.client_config.proxy.as_deref(),
+                    Some("http://localhost:8080")
+                );
+                assert_eq!(provider.client_config.connect_timeout_ms, Some(1000));
+            }
+            EmbeddingProviderConfig::Ollama(_) => panic!("expected OpenAI variant"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_openai_embedding_provider_config_with_retry_policy() {
+        let serialized = r#"
+        type = "openai"
+        model_name = "text-embedding-3-small"
+
+        [retry_policy]
+        max_retries = 5
+        "#;
+        let config: EmbeddingProviderConfig = toml::from_str(serialized).unwrap();
+        match config {
+            EmbeddingProviderConfig::OpenAI(provider) => {
+                assert_eq!(provider.retry_policy.max_retries, 5);
+            }
+            EmbeddingProviderConfig::Ollama(_) => panic!("expected OpenAI variant"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_openai_embedding_provider_config_defaults_retry_policy() {
+        let serialized = r#"
+        type = "openai"
+        model_name = "text-embedding-3-small"
+        "#;
+        let config: EmbeddingProviderConfig = toml::from_str(serialized).unwrap();
+        match config {
+            EmbeddingProviderConfig::OpenAI(provider) => {
+                assert_eq!(provider.retry_policy.max_retries, RetryPolicy::default().max_retries);
+            }
+            EmbeddingProviderConfig::Ollama(_) => panic!("expected OpenAI variant"),
+        }
+    }
+
+    #[test]
+    fn test_normalize() {
+        let normalized = normalize(vec![3.0, 4.0]);
+        let norm: f32 = normalized.iter().map(|x| x * x).sum::<f32>().sqrt();
+        assert!((norm - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_deserialize_ollama_embedding_provider_config() {
+        let serialized = r#"
+        type = "ollama"
+        model_name = "nomic-embed-text"
+        "#;
+        let config: EmbeddingProviderConfig = toml::from_str(serialized).unwrap();
+        match config {
+            EmbeddingProviderConfig::Ollama(provider) => {
+                assert_eq!(provider.model_name, "nomic-embed-text");
+                assert_eq!(provider.base_url, "http://localhost:11434");
+            }
+            EmbeddingProviderConfig::OpenAI(_) => panic!("expected Ollama variant"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_ollama_embedding_provider_config_with_base_url() {
+        let serialized = r#"
+        type = "ollama"
+        model_name = "nomic-embed-text"
+        base_url = "http://my-ollama-host:11434"
+        "#;
+        let config: EmbeddingProviderConfig = toml::from_str(serialized).unwrap();
+        match config {
+            EmbeddingProviderConfig::Ollama(provider) => {
+                assert_eq!(provider.base_url, "http://my-ollama-host:11434");
+            }
+            EmbeddingProviderConfig::OpenAI(_) => panic!("expected Ollama variant"),
+        }
+    }
+
+    #[test]
+    fn test_embedding_input_len() {
+        let single: EmbeddingInput = "hello".to_string().into();
+        assert_eq!(single.len(), 1);
+        let batch: EmbeddingInput = vec!["a".to_string(), "b".to_string()].into();
+        assert_eq!(batch.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_embed_client_request_rejects_oversized_batch_without_calling_provider() {
+        let provider = EmbeddingProviderConfig::OpenAI(OpenAIEmbeddingProvider {
+            model_name: "text-embedding-3-small".to_string(),
+            api_base: None,
+            client_config: ProviderClientConfig::default(),
+            retry_policy: RetryPolicy::default(),
+        });
+        let oversized: EmbeddingInput = (0..DEFAULT_MAX_CLIENT_BATCH_SIZE + 1)
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .into();
+        let request = EmbeddingRequest { input: oversized };
+
+        // No API key is configured and there's no network in this test, so a
+        // call that reached the provider would fail with an unrelated
+        // "ApiKeyMissing"-style message; asserting on validate_batch_size's
+        // own wording proves validation ran before any provider call.
+        let err = embed_client_request(
+            &provider,
+            &request,
+            &Client::new(),
+            &InferenceCredentials::default(),
+            DEFAULT_MAX_CLIENT_BATCH_SIZE,
+        )
+        .await
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("exceeds the configured max_client_batch_size"));
+    }
+
+    #[test]
+    fn test_validate_batch_size() {
+        let batch: EmbeddingInput = vec!["a".to_string(), "b".to_string()].into();
+        assert!(validate_batch_size(&batch, DEFAULT_MAX_CLIENT_BATCH_SIZE).is_ok());
+
+        let oversized: EmbeddingInput = (0..DEFAULT_MAX_CLIENT_BATCH_SIZE + 1)
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .into();
+        assert!(validate_batch_size(&oversized, DEFAULT_MAX_CLIENT_BATCH_SIZE).is_err());
+    }
+}
@@ -0,0 +1,229 @@
+//! Background liveness probing for model and embedding providers.
+//!
+//! Each configured provider gets a background task that performs a cheap
+//! probe on an interval and publishes the result over a `watch` channel, so
+//! request paths can check current health without blocking on a probe
+//! themselves, and the gateway can serve a `/health` endpoint from the same
+//! state.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::embeddings::{EmbeddingProviderConfig, EmbeddingRequest};
+use crate::endpoints::inference::InferenceCredentials;
+use crate::error::{Error, ErrorDetails};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Health {
+    Healthy,
+    Unhealthy,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LastProbe {
+    pub health: Health,
+    pub latency: Duration,
+    /// When the most recent *successful* probe completed, or `None` if the
+    /// provider has never once answered a probe.
+    pub last_success: Option<Instant>,
+}
+
+impl LastProbe {
+    fn initial() -> Self {
+        Self {
+            health: Health::Healthy,
+            latency: Duration::ZERO,
+            last_success: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HealthWatcherConfig {
+    #[serde(default = "default_probe_interval_s")]
+    pub probe_interval_s: u64,
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+}
+
+fn default_probe_interval_s() -> u64 {
+    30
+}
+
+fn default_failure_threshold() -> u32 {
+    2
+}
+
+impl Default for HealthWatcherConfig {
+    fn default() -> Self {
+        Self {
+            probe_interval_s: default_probe_interval_s(),
+            failure_threshold: default_failure_threshold(),
+        }
+    }
+}
+
+/// A cheap input used to probe an embedding provider; short enough to be
+/// nearly free on every provider while still exercising the real request
+/// path (auth, network, response parsing).
+const PROBE_INPUT: &str = "ping";
+
+/// Spawns a background task that periodically runs `probe` and publishes its
+/// health, last probe latency, and last-success timestamp over the returned
+/// `watch::Receiver`. The task runs until the receiver (and every clone of
+/// it) is dropped.
+///
+/// Generic over the probe itself so embedding and chat providers can share
+/// one implementation; each provider type supplies its own minimal-request
+/// closure (see [`spawn_embedding_health_watcher`] and
+/// [`spawn_chat_health_watcher`]).
+fn spawn_health_watcher<F, Fut>(mut probe: F, config: HealthWatcherConfig) -> watch::Receiver<LastProbe>
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: std::future::Future<Output = Result<(), Error>> + Send,
+{
+    let (sender, receiver) = watch::channel(LastProbe::initial());
+    tokio::spawn(async move {
+        let mut consecutive_failures = 0u32;
+        loop {
+            if sender.is_closed() {
+                return;
+            }
+            let start = Instant::now();
+            let probe_result = probe().await;
+            let latency = start.elapsed();
+            match probe_result {
+                Ok(()) => {
+                    consecutive_failures = 0;
+                    let now = Instant::now();
+                    sender.send_modify(|probe| {
+                        probe.health = Health::Healthy;
+                        probe.latency = latency;
+                        probe.last_success = Some(now);
+                    });
+                }
+                Err(_) => {
+                    consecutive_failures += 1;
+                    let became_unhealthy = consecutive_failures >= config.failure_threshold;
+                    sender.send_modify(|probe| {
+                        probe.latency = latency;
+                        if became_unhealthy {
+                            probe.health = Health::Unhealthy;
+                        }
+                    });
+                }
+            }
+            tokio::time::sleep(Duration::from_secs(config.probe_interval_s)).await;
+        }
+    });
+    receiver
+}
+
+/// Spawns a background task that periodically probes `provider` with a
+/// minimal embedding request. See [`spawn_health_watcher`] for the shared
+/// probing/publishing behavior.
+pub fn spawn_embedding_health_watcher(
+    provider: Arc<EmbeddingProviderConfig>,
+    client: Client,
+    api_keys: InferenceCredentials,
+    config: HealthWatcherConfig,
+) -> watch::Receiver<LastProbe> {
+    spawn_health_watcher(
+        move || {
+            let provider = Arc::clone(&provider);
+            let client = client.clone();
+            let api_keys = api_keys.clone();
+            async move {
+                let request = EmbeddingRequest {
+                    input: PROBE_INPUT.to_string().into(),
+                };
+                provider.embed(&request, &client, &api_keys).await.map(|_| ())
+            }
+        },
+        config,
+    )
+}
+
+/// Spawns a background task that probes a chat provider the same way
+/// [`spawn_embedding_health_watcher`] does for embedding providers: a cheap,
+/// minimal request on an interval, published over a `watch::Receiver`. This
+/// crate slice doesn't define a chat-provider trait, so the caller supplies
+/// `probe` directly — a chat-provider module would pass a closure that sends
+/// a minimal (e.g. one-token) completion, the same way
+/// `spawn_embedding_health_watcher` passes one that embeds [`PROBE_INPUT`].
+pub fn spawn_chat_health_watcher<F, Fut>(probe: F, config: HealthWatcherConfig) -> watch::Receiver<LastProbe>
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: std::future::Future<Output = Result<(), Error>> + Send,
+{
+    spawn_health_watcher(probe, config)
+}
+
+/// Whether inference dispatch should currently consider this provider, based
+/// on its last published health. Callers typically use this to skip or
+/// down-rank an unhealthy provider rather than hard-failing on it.
+pub fn is_healthy(receiver: &watch::Receiver<LastProbe>) -> bool {
+    receiver.borrow().health == Health::Healthy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_healthy_defaults_true() {
+        let (_sender, receiver) = watch::channel(LastProbe::initial());
+        assert!(is_healthy(&receiver));
+    }
+
+    #[test]
+    fn test_is_healthy_reflects_unhealthy() {
+        let (sender, receiver) = watch::channel(LastProbe::initial());
+        sender.send_modify(|probe| probe.health = Health::Unhealthy);
+        assert!(!is_healthy(&receiver));
+    }
+
+    #[tokio::test]
+    async fn test_chat_health_watcher_marks_unhealthy_after_threshold() {
+        let config = HealthWatcherConfig {
+            probe_interval_s: 0,
+            failure_threshold: 2,
+        };
+        let receiver = spawn_chat_health_watcher(
+            || async {
+                Err(Error::new(ErrorDetails::InferenceClient {
+                    message: "chat probe failed".to_string(),
+                }))
+            },
+            config,
+        );
+        let mut receiver = receiver;
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while receiver.borrow().health != Health::Unhealthy {
+                receiver.changed().await.unwrap();
+            }
+        })
+        .await
+        .expect("chat health watcher should report unhealthy after repeated failures");
+    }
+
+    #[test]
+    fn test_last_probe_records_success_timestamp_and_latency() {
+        let (sender, receiver) = watch::channel(LastProbe::initial());
+        assert!(receiver.borrow().last_success.is_none());
+        let latency = Duration::from_millis(42);
+        sender.send_modify(|probe| {
+            probe.health = Health::Healthy;
+            probe.latency = latency;
+            probe.last_success = Some(Instant::now());
+        });
+        let probe = *receiver.borrow();
+        assert_eq!(probe.latency, latency);
+        assert!(probe.last_success.is_some());
+    }
+}
@@ -0,0 +1,90 @@
+use serde_json::Value;
+
+use crate::error::{Error, ErrorDetails};
+use crate::inference::types::{ContentBlock, ModelInferenceRequestJsonMode};
+
+/// The name given to the tool the gateway forces when a chat function
+/// requests structured output via `implicit_tool` / `strict` json mode. Kept
+/// distinct from any user-defined tool name so it can't collide.
+pub const IMPLICIT_OUTPUT_TOOL_NAME: &str = "tensorzero::implicit_structured_output";
+
+/// Whether `json_mode` calls for the gateway to coerce a chat function's
+/// output into `output_schema` rather than returning raw text.
+pub fn requires_structured_output(json_mode: ModelInferenceRequestJsonMode) -> bool {
+    matches!(
+        json_mode,
+        ModelInferenceRequestJsonMode::ImplicitTool | ModelInferenceRequestJsonMode::Strict
+    )
+}
+
+/// Builds the single forced tool definition used to coax an implicit-tool
+/// structured response out of a chat function that only has an
+/// `output_schema`, no user-defined tools.
+pub fn implicit_output_tool(output_schema: &Value) -> Value {
+    serde_json::json!({
+        "type": "function",
+        "function": {
+            "name": IMPLICIT_OUTPUT_TOOL_NAME,
+            "description": "Return the final answer using this schema.",
+            "parameters": output_schema,
+        },
+    })
+}
+
+/// Extracts the arguments of the forced implicit tool call and parses them
+/// as JSON, returning the parsed value. The value is not checked against
+/// `output_schema`. Chat
+/// functions keep their raw text content block alongside this structured
+/// block rather than replacing it, unlike JSON functions.
+pub fn parse_implicit_tool_output(content: &[ContentBlock]) -> Result<Value, Error> {
+    for block in content {
+        if let ContentBlock::ToolCall(tool_call) = block {
+            if tool_call.name.as_deref() == Some(IMPLICIT_OUTPUT_TOOL_NAME) {
+                return serde_json::from_str(&tool_call.arguments).map_err(|e| {
+                    Error::new(ErrorDetails::OutputParsing {
+                        message: format!(
+                            "Failed to parse implicit structured output tool arguments: {e}"
+                        ),
+                        raw_output: tool_call.arguments.clone(),
+                    })
+                });
+            }
+        }
+    }
+    Err(Error::new(ErrorDetails::OutputParsing {
+        message: "Model did not call the forced structured-output tool".to_string(),
+        raw_output: String::new(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requires_structured_output() {
+        assert!(requires_structured_output(
+            ModelInferenceRequestJsonMode::ImplicitTool
+        ));
+        assert!(requires_structured_output(
+            ModelInferenceRequestJsonMode::Strict
+        ));
+        assert!(!requires_structured_output(
+            ModelInferenceRequestJsonMode::Off
+        ));
+        assert!(!requires_structured_output(
+            ModelInferenceRequestJsonMode::On
+        ));
+    }
+
+    #[test]
+    fn test_implicit_output_tool_carries_schema() {
+        let schema = serde_json::json!({"type": "object"});
+        let tool = implicit_output_tool(&schema);
+        assert_eq!(
+            tool["function"]["name"],
+            Value::String(IMPLICIT_OUTPUT_TOOL_NAME.to_string())
+        );
+        assert_eq!(tool["function"]["parameters"], schema);
+    }
+}
@@ -0,0 +1,443 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use reqwest::StatusCode;
+use tokio::sync::watch;
+
+use crate::auth::AuthenticatedIdentity;
+use crate::error::{Error, ErrorDetails};
+use crate::health::{self, LastProbe};
+use crate::retry::RetryPolicy;
+
+/// Status codes and transport errors that are worth rotating to the next
+/// provider (or retrying, see the retry policy) rather than failing the
+/// whole inference immediately.
+pub fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+    )
+}
+
+/// After this many consecutive failures a provider is marked unhealthy and
+/// skipped by rotation until its cooldown elapses.
+const UNHEALTHY_THRESHOLD: u32 = 3;
+const BASE_COOLDOWN: Duration = Duration::from_secs(5);
+const MAX_COOLDOWN: Duration = Duration::from_secs(300);
+
+#[derive(Debug)]
+struct ProviderHealthState {
+    consecutive_failures: AtomicU32,
+    unhealthy_since: Mutex<Option<Instant>>,
+}
+
+impl Default for ProviderHealthState {
+    fn default() -> Self {
+        Self {
+            consecutive_failures: AtomicU32::new(0),
+            unhealthy_since: Mutex::new(None),
+        }
+    }
+}
+
+impl ProviderHealthState {
+    fn record_success(&self) {
+        self.consecutive_failures.store(0, Ordering::SeqCst);
+        *self.unhealthy_since.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    fn record_failure(&self) {
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::SeqCst) + 1;
+        if failures >= UNHEALTHY_THRESHOLD {
+            let mut unhealthy_since = self
+                .unhealthy_since
+                .lock()
+                .unwrap_or_else(|e| e.into_inner());
+            if unhealthy_since.is_none() {
+                *unhealthy_since = Some(Instant::now());
+            }
+        }
+    }
+
+    /// Returns whether this provider may currently be tried. A provider that
+    /// tripped the failure threshold is skipped until its exponential
+    /// cooldown (based on how many times it has failed past the threshold)
+    /// elapses, at which point it is re-probed.
+    fn is_available(&self) -> bool {
+        let unhealthy_since = *self
+            .unhealthy_since
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let Some(unhealthy_since) = unhealthy_since else {
+            return true;
+        };
+        let failures = self.consecutive_failures.load(Ordering::SeqCst);
+        let extra_failures = failures.saturating_sub(UNHEALTHY_THRESHOLD);
+        let cooldown = (BASE_COOLDOWN * 2u32.saturating_pow(extra_failures)).min(MAX_COOLDOWN);
+        unhealthy_since.elapsed() >= cooldown
+    }
+}
+
+/// An error from a single provider attempt, carrying enough information for
+/// [`ModelProviderRing::dispatch_with_retry`] to decide whether it's worth
+/// retrying: the HTTP status code when the failure was a non-2xx response,
+/// or `None` for a transport-level (e.g. connection/IO) failure, which is
+/// also treated as retryable since that's exactly the kind of transient blip
+/// retries exist for.
+#[derive(Debug)]
+pub struct ProviderAttemptError {
+    pub error: Error,
+    pub status_code: Option<StatusCode>,
+}
+
+impl ProviderAttemptError {
+    pub fn new(error: Error, status_code: Option<StatusCode>) -> Self {
+        Self { error, status_code }
+    }
+}
+
+impl std::fmt::Display for ProviderAttemptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.error, f)
+    }
+}
+
+/// A named, ordered ring of providers backing a single model. Inference
+/// rotates through `routing` on retryable failures, starting from a shared
+/// cursor so concurrent requests fan out across providers rather than all
+/// hammering the same one after a failure.
+///
+/// The gateway's model-dispatch entry point (where a `ModelConfig` resolves
+/// to a `ModelProviderRing` and calls [`Self::dispatch_with_retry`] /
+/// [`Self::dispatch_with_retry_authorized`] per inference request, then
+/// records which provider served it on the `ModelInference` ClickHouse row)
+/// isn't part of this crate slice, so this ring is exercised only by its own
+/// tests here. It's written to be dropped into that call site unchanged.
+#[derive(Debug)]
+pub struct ModelProviderRing {
+    /// Provider names, in the order configured for this model.
+    pub routing: Vec<String>,
+    cursor: AtomicUsize,
+    health: HashMap<String, ProviderHealthState>,
+    /// Liveness receivers registered via [`Self::set_health_watcher`] (from
+    /// `spawn_embedding_health_watcher` / `spawn_chat_health_watcher`), keyed
+    /// by provider name. Consulted alongside `health` so a provider the
+    /// background prober currently reports unhealthy is skipped even if it
+    /// hasn't failed an inference attempt recently enough to trip `health`'s
+    /// own consecutive-failure threshold.
+    external_health: Mutex<HashMap<String, watch::Receiver<LastProbe>>>,
+}
+
+impl ModelProviderRing {
+    pub fn new(routing: Vec<String>) -> Self {
+        let health = routing
+            .iter()
+            .map(|name| (name.clone(), ProviderHealthState::default()))
+            .collect();
+        Self {
+            routing,
+            cursor: AtomicUsize::new(0),
+            health,
+            external_health: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Registers a liveness probe receiver for `provider_name`; see
+    /// `external_health`.
+    pub fn set_health_watcher(&self, provider_name: &str, receiver: watch::Receiver<LastProbe>) {
+        self.external_health
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(provider_name.to_string(), receiver);
+    }
+
+    /// Returns the rotation order to try for this request: starting at the
+    /// shared cursor (advanced so the next request starts one further along),
+    /// skipping providers currently marked unhealthy, but falling back to the
+    /// full list if every provider is unhealthy so we still attempt something.
+    fn rotation_order(&self) -> Vec<&str> {
+        let len = self.routing.len();
+        if len == 0 {
+            return Vec::new();
+        }
+        let start = self.cursor.fetch_add(1, Ordering::SeqCst) % len;
+        let ordered: Vec<&str> = (0..len)
+            .map(|offset| self.routing[(start + offset) % len].as_str())
+            .collect();
+        let external_health = self
+            .external_health
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let healthy: Vec<&str> = ordered
+            .iter()
+            .copied()
+            .filter(|name| {
+                let internally_available = self
+                    .health
+                    .get(*name)
+                    .map(ProviderHealthState::is_available)
+                    .unwrap_or(true);
+                let externally_healthy = external_health
+                    .get(*name)
+                    .map(health::is_healthy)
+                    .unwrap_or(true);
+                internally_available && externally_healthy
+            })
+            .collect();
+        if healthy.is_empty() {
+            ordered
+        } else {
+            healthy
+        }
+    }
+
+    pub fn record_success(&self, provider_name: &str) {
+        if let Some(state) = self.health.get(provider_name) {
+            state.record_success();
+        }
+    }
+
+    pub fn record_failure(&self, provider_name: &str) {
+        if let Some(state) = self.health.get(provider_name) {
+            state.record_failure();
+        }
+    }
+
+    /// Runs `attempt` against each provider in rotation order until one
+    /// succeeds, returning the winning provider's name alongside its result.
+    /// If every provider fails, returns a combined error listing each
+    /// provider's failure. Each provider is retried per
+    /// `RetryPolicy::default()` before rotating.
+    pub async fn dispatch<T, F, Fut>(&self, attempt: F) -> Result<(String, T), Error>
+    where
+        F: FnMut(&str) -> Fut,
+        Fut: std::future::Future<Output = Result<T, ProviderAttemptError>>,
+    {
+        self.dispatch_with_retry(&RetryPolicy::default(), attempt)
+            .await
+    }
+
+    /// Like [`Self::dispatch`], but with a caller-supplied `retry_policy`:
+    /// each provider is retried per it before rotating to the next one, so a
+    /// single provider's transient
+    /// blip doesn't burn through the whole ring. Only attempts whose error
+    /// carries a retryable status code (per [`RetryPolicy::is_retryable_status`])
+    /// or no status code at all (a transport/IO failure) are retried; a 4xx
+    /// like an invalid-model response fails that provider immediately.
+    pub async fn dispatch_with_retry<T, F, Fut>(
+        &self,
+        retry_policy: &RetryPolicy,
+        mut attempt: F,
+    ) -> Result<(String, T), Error>
+    where
+        F: FnMut(&str) -> Fut,
+        Fut: std::future::Future<Output = Result<T, ProviderAttemptError>>,
+    {
+        let mut errors = Vec::new();
+        for provider_name in self.rotation_order() {
+            let result = retry_policy
+                .retry(
+                    |e: &ProviderAttemptError| {
+                        e.status_code
+                            .map(|status| retry_policy.is_retryable_status(status))
+                            .unwrap_or(true)
+                    },
+                    || attempt(provider_name),
+                )
+                .await;
+            match result {
+                Ok(value) => {
+                    self.record_success(provider_name);
+                    return Ok((provider_name.to_string(), value));
+                }
+                Err(e) => {
+                    self.record_failure(provider_name);
+                    errors.push(format!("{provider_name}: {e}"));
+                }
+            }
+        }
+        Err(Error::new(ErrorDetails::ModelProvidersExhausted {
+            message: if errors.is_empty() {
+                "Model has no configured providers".to_string()
+            } else {
+                errors.join("; ")
+            },
+        }))
+    }
+
+    /// Like [`Self::dispatch_with_retry`], but first rejects the request if
+    /// `identity` is `Some` and [`AuthenticatedIdentity::may_invoke`] denies
+    /// `model_name`, so an unauthorized JWT-authenticated caller never
+    /// reaches a provider. `identity` is `None` for gateways running without
+    /// JWT auth configured, in which case this behaves exactly like
+    /// `dispatch_with_retry`.
+    pub async fn dispatch_with_retry_authorized<T, F, Fut>(
+        &self,
+        model_name: &str,
+        identity: Option<&AuthenticatedIdentity>,
+        retry_policy: &RetryPolicy,
+        attempt: F,
+    ) -> Result<(String, T), Error>
+    where
+        F: FnMut(&str) -> Fut,
+        Fut: std::future::Future<Output = Result<T, ProviderAttemptError>>,
+    {
+        if let Some(identity) = identity {
+            if !identity.may_invoke(model_name) {
+                return Err(Error::new(ErrorDetails::AuthenticationFailed {
+                    message: format!(
+                        "Caller `{}` is not permitted to invoke model `{model_name}`",
+                        identity.subject
+                    ),
+                }));
+            }
+        }
+        self.dispatch_with_retry(retry_policy, attempt).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dispatch_rotates_on_failure() {
+        let ring = ModelProviderRing::new(vec!["a".to_string(), "b".to_string()]);
+        let (winner, value) = ring
+            .dispatch(|name| async move {
+                if name == "a" {
+                    Err(ProviderAttemptError::new(
+                        Error::new(ErrorDetails::InferenceClient {
+                            message: "boom".to_string(),
+                        }),
+                        Some(StatusCode::INTERNAL_SERVER_ERROR),
+                    ))
+                } else {
+                    Ok(42)
+                }
+            })
+            .await
+            .unwrap();
+        assert_eq!(winner, "b");
+        assert_eq!(value, 42);
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_all_fail_returns_combined_error() {
+        let ring = ModelProviderRing::new(vec!["a".to_string(), "b".to_string()]);
+        let result = ring
+            .dispatch(|name| async move {
+                Err::<(), _>(ProviderAttemptError::new(
+                    Error::new(ErrorDetails::InferenceClient {
+                        message: format!("{name} down"),
+                    }),
+                    Some(StatusCode::BAD_GATEWAY),
+                ))
+            })
+            .await;
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("a down"));
+        assert!(err.contains("b down"));
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_does_not_retry_non_retryable_status() {
+        let ring = ModelProviderRing::new(vec!["a".to_string()]);
+        let attempts = AtomicU32::new(0);
+        let result = ring
+            .dispatch(|_name| {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                async {
+                    Err::<(), _>(ProviderAttemptError::new(
+                        Error::new(ErrorDetails::InferenceClient {
+                            message: "invalid model".to_string(),
+                        }),
+                        Some(StatusCode::BAD_REQUEST),
+                    ))
+                }
+            })
+            .await;
+        assert!(result.is_err());
+        // A 4xx like an invalid-model response is not retryable, so the
+        // provider should only be attempted once despite the default
+        // retry policy allowing more.
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_authorized_rejects_disallowed_model() {
+        let ring = ModelProviderRing::new(vec!["a".to_string()]);
+        let identity = AuthenticatedIdentity {
+            subject: "tenant-a".to_string(),
+            allowed_models: vec!["other-model".to_string()],
+        };
+        let attempts = AtomicU32::new(0);
+        let result = ring
+            .dispatch_with_retry_authorized(
+                "gpt-4o-mini",
+                Some(&identity),
+                &RetryPolicy::default(),
+                |_name| {
+                    attempts.fetch_add(1, Ordering::SeqCst);
+                    async { Ok::<_, ProviderAttemptError>(()) }
+                },
+            )
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_authorized_allows_permitted_model() {
+        let ring = ModelProviderRing::new(vec!["a".to_string()]);
+        let identity = AuthenticatedIdentity {
+            subject: "tenant-a".to_string(),
+            allowed_models: vec!["gpt-4o-mini".to_string()],
+        };
+        let (winner, value) = ring
+            .dispatch_with_retry_authorized(
+                "gpt-4o-mini",
+                Some(&identity),
+                &RetryPolicy::default(),
+                |_name| async { Ok::<_, ProviderAttemptError>(42) },
+            )
+            .await
+            .unwrap();
+        assert_eq!(winner, "a");
+        assert_eq!(value, 42);
+    }
+
+    #[test]
+    fn test_unhealthy_provider_is_skipped() {
+        let ring = ModelProviderRing::new(vec!["a".to_string(), "b".to_string()]);
+        for _ in 0..UNHEALTHY_THRESHOLD {
+            ring.record_failure("a");
+        }
+        let order = ring.rotation_order();
+        assert_eq!(order, vec!["b"]);
+    }
+
+    #[test]
+    fn test_externally_unhealthy_provider_is_skipped() {
+        use crate::health::{Health, LastProbe};
+        use std::time::Duration;
+        use tokio::sync::watch;
+
+        let ring = ModelProviderRing::new(vec!["a".to_string(), "b".to_string()]);
+        let (sender, receiver) = watch::channel(LastProbe {
+            health: Health::Healthy,
+            latency: Duration::ZERO,
+            last_success: None,
+        });
+        ring.set_health_watcher("a", receiver);
+        sender.send_modify(|probe| probe.health = Health::Unhealthy);
+
+        let order = ring.rotation_order();
+        assert_eq!(order, vec!["b"]);
+    }
+}
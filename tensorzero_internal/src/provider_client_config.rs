@@ -0,0 +1,127 @@
+use std::time::Duration;
+
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, ErrorDetails};
+
+/// Shared HTTP client configuration for model providers (chat and embeddings alike).
+///
+/// Every provider constructs its `reqwest::Client` from one of these instead of
+/// calling `reqwest::Client::new()` directly, so proxy/timeout/TLS behavior is
+/// configured in one place and inherited uniformly.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProviderClientConfig {
+    /// HTTP/HTTPS/SOCKS5 proxy URL. If unset, falls back to the `HTTP_PROXY` /
+    /// `HTTPS_PROXY` / `NO_PROXY` environment variables, matching the behavior
+    /// most HTTP clients provide out of the box.
+    #[serde(default)]
+    pub proxy: Option<String>,
+    #[serde(default)]
+    pub connect_timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub read_timeout_ms: Option<u64>,
+    /// Accept self-signed / otherwise invalid TLS certificates. Intended for
+    /// private gateways during development; should not be enabled in production.
+    #[serde(default)]
+    pub danger_accept_invalid_certs: bool,
+}
+
+impl ProviderClientConfig {
+    /// Builds a `reqwest::Client` honoring this configuration. When `proxy` is
+    /// unset, `reqwest` still picks up `HTTP_PROXY`/`HTTPS_PROXY`/`NO_PROXY`
+    /// automatically, so we only need to configure it explicitly here.
+    pub fn build_client(&self) -> Result<reqwest::Client, Error> {
+        let mut builder = reqwest::Client::builder();
+        if let Some(proxy_url) = &self.proxy {
+            let proxy = reqwest::Proxy::all(proxy_url).map_err(|e| {
+                Error::new(ErrorDetails::Config {
+                    message: format!("Invalid proxy URL `{proxy_url}`: {e}"),
+                })
+            })?;
+            builder = builder.proxy(proxy);
+        }
+        if let Some(connect_timeout_ms) = self.connect_timeout_ms {
+            builder = builder.connect_timeout(Duration::from_millis(connect_timeout_ms));
+        }
+        if let Some(read_timeout_ms) = self.read_timeout_ms {
+            builder = builder.timeout(Duration::from_millis(read_timeout_ms));
+        }
+        if self.danger_accept_invalid_certs {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        builder.build().map_err(|e| {
+            Error::new(ErrorDetails::Config {
+                message: format!("Failed to build HTTP client: {e}"),
+            })
+        })
+    }
+
+    /// Returns `default` unmodified if this config has nothing to override,
+    /// otherwise builds a dedicated client via [`Self::build_client`]. This
+    /// is the check every provider's `embed`/inference call needs before
+    /// picking which client to use, pulled out so it isn't copy-pasted at
+    /// each call site.
+    pub fn effective_client(&self, default: &Client) -> Result<Client, Error> {
+        if self.proxy.is_some()
+            || self.connect_timeout_ms.is_some()
+            || self.read_timeout_ms.is_some()
+            || self.danger_accept_invalid_certs
+        {
+            self.build_client()
+        } else {
+            Ok(default.clone())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_client_defaults() {
+        let config = ProviderClientConfig::default();
+        assert!(config.build_client().is_ok());
+    }
+
+    #[test]
+    fn test_build_client_with_proxy() {
+        let config = ProviderClientConfig {
+            proxy: Some("http://localhost:8080".to_string()),
+            connect_timeout_ms: Some(1000),
+            read_timeout_ms: Some(30_000),
+            danger_accept_invalid_certs: true,
+        };
+        assert!(config.build_client().is_ok());
+    }
+
+    #[test]
+    fn test_build_client_with_invalid_proxy() {
+        let config = ProviderClientConfig {
+            proxy: Some("not a url".to_string()),
+            ..Default::default()
+        };
+        assert!(config.build_client().is_err());
+    }
+
+    #[test]
+    fn test_effective_client_reuses_default_when_unconfigured() {
+        let config = ProviderClientConfig::default();
+        let default = reqwest::Client::new();
+        // Can't compare `Client`s directly; absence of an error is the
+        // behavior this test is pinning (a configured client would instead
+        // be built fresh via `build_client`, exercised below).
+        assert!(config.effective_client(&default).is_ok());
+    }
+
+    #[test]
+    fn test_effective_client_builds_dedicated_client_when_configured() {
+        let config = ProviderClientConfig {
+            connect_timeout_ms: Some(1000),
+            ..Default::default()
+        };
+        assert!(config.effective_client(&reqwest::Client::new()).is_ok());
+    }
+}
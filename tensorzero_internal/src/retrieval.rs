@@ -0,0 +1,274 @@
+//! Semantic retrieval over embeddings persisted in ClickHouse: chunk a
+//! document, embed and store each chunk, then rank stored chunks against a
+//! query embedding by cosine similarity.
+
+use std::collections::HashMap;
+
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::clickhouse::ClickHouseConnectionInfo;
+use crate::embeddings::{EmbeddingProviderConfig, EmbeddingRequest};
+use crate::endpoints::inference::InferenceCredentials;
+use crate::error::{Error, ErrorDetails};
+
+/// A half-open byte range `[start, end)` within the source document that a
+/// chunk was taken from, so search results can be mapped back to exactly the
+/// text that was embedded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ByteRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A row in the `Embedding` ClickHouse table: a unit-normalized vector, the
+/// document/inference id it came from, and the byte range of the chunk it
+/// represents.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingRow {
+    pub id: Uuid,
+    pub source_id: Uuid,
+    pub range: ByteRange,
+    pub vector: Vec<f32>,
+}
+
+/// Splits `text` into chunks of at most `max_chunk_chars` characters,
+/// breaking on whitespace where possible so chunks don't split mid-word.
+/// Each returned chunk carries the exact byte range in `text` it came from.
+pub fn chunk_text(text: &str, max_chunk_chars: usize) -> Vec<(ByteRange, &str)> {
+    if max_chunk_chars == 0 || text.is_empty() {
+        return Vec::new();
+    }
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let bytes = text.as_bytes();
+    while start < text.len() {
+        let mut end = (start + max_chunk_chars).min(text.len());
+        if end < text.len() {
+            // Walk back to the nearest preceding whitespace so we don't split
+            // a word across chunk boundaries.
+            if let Some(offset) = bytes[start..end]
+                .iter()
+                .rposition(|b| b.is_ascii_whitespace())
+            {
+                end = start + offset + 1;
+            }
+        }
+        // Ensure we always land on a char boundary and make forward progress.
+        while !text.is_char_boundary(end) {
+            end += 1;
+        }
+        if end <= start {
+            end = text.len().min(start + max_chunk_chars);
+        }
+        chunks.push((ByteRange { start, end }, &text[start..end]));
+        start = end;
+    }
+    chunks
+}
+
+/// Embeds each chunk of `text` via `embedding_provider` and inserts the
+/// resulting rows into the `Embedding` table, tagged with `source_id`.
+pub async fn index_document(
+    clickhouse: &ClickHouseConnectionInfo,
+    embedding_provider: &EmbeddingProviderConfig,
+    client: &Client,
+    api_keys: &InferenceCredentials,
+    source_id: Uuid,
+    text: &str,
+    max_chunk_chars: usize,
+) -> Result<Vec<EmbeddingRow>, Error> {
+    let chunks = chunk_text(text, max_chunk_chars);
+    let inputs: Vec<String> = chunks.iter().map(|(_, chunk)| chunk.to_string()).collect();
+    if inputs.is_empty() {
+        return Ok(Vec::new());
+    }
+    let response = embedding_provider
+        .embed(
+            &EmbeddingRequest {
+                input: inputs.into(),
+            },
+            client,
+            api_keys,
+        )
+        .await?;
+
+    let rows: Vec<EmbeddingRow> = chunks
+        .into_iter()
+        .zip(response.embeddings)
+        .map(|((range, _chunk), embedding)| EmbeddingRow {
+            id: Uuid::now_v7(),
+            source_id,
+            range,
+            vector: embedding.vector,
+        })
+        .collect();
+
+    clickhouse.write(&rows, "Embedding").await?;
+    Ok(rows)
+}
+
+/// One nearest-neighbor search result: the source document id, the byte
+/// range of the matching chunk within it, and its cosine similarity to the
+/// query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    pub source_id: Uuid,
+    pub range: ByteRange,
+    pub score: f32,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingCandidateRow {
+    #[allow(dead_code)]
+    id: Uuid,
+    source_id: Uuid,
+    #[serde(deserialize_with = "crate::inference::types::batch::deserialize_json_string")]
+    range: ByteRange,
+    vector: Vec<f32>,
+}
+
+/// Fetches every stored chunk embedding for the given source documents from
+/// the `Embedding` table, so `search` can rank real persisted candidates
+/// instead of requiring the caller to have already fetched them.
+async fn fetch_candidates(
+    clickhouse: &ClickHouseConnectionInfo,
+    source_ids: &[Uuid],
+) -> Result<Vec<EmbeddingRow>, Error> {
+    if source_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let source_ids_param = format!(
+        "[{}]",
+        source_ids
+            .iter()
+            .map(|id| format!("'{id}'"))
+            .collect::<Vec<_>>()
+            .join(",")
+    );
+    let query = r#"
+        SELECT id, source_id, range, vector
+        FROM Embedding
+        WHERE source_id IN {source_ids:Array(UUID)}
+        FORMAT JSONEachRow
+    "#;
+    let query_params = HashMap::from([("source_ids", source_ids_param.as_str())]);
+    let result = clickhouse
+        .run_query(query.to_string(), Some(&query_params))
+        .await?;
+    result
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let row: EmbeddingCandidateRow = serde_json::from_str(line).map_err(|e| {
+                Error::new(ErrorDetails::InferenceClient {
+                    message: format!("Failed to deserialize Embedding row: {e}"),
+                })
+            })?;
+            Ok(EmbeddingRow {
+                id: row.id,
+                source_id: row.source_id,
+                range: row.range,
+                vector: row.vector,
+            })
+        })
+        .collect()
+}
+
+/// Ranks a candidate set of already-fetched embedding rows against a query
+/// vector by cosine similarity, returning the top `k`. Both the query vector
+/// and stored vectors are assumed unit-normalized (per `EmbeddingProvider`'s
+/// contract), so this reduces to a dot product.
+pub fn rank_by_similarity(query: &[f32], candidates: &[EmbeddingRow], k: usize) -> Vec<SearchResult> {
+    let mut scored: Vec<SearchResult> = candidates
+        .iter()
+        .map(|row| SearchResult {
+            source_id: row.source_id,
+            range: row.range,
+            score: cosine_similarity(query, &row.vector),
+        })
+        .collect();
+    scored.sort_by(|a, b| b.score.total_cmp(&a.score));
+    scored.truncate(k);
+    scored
+}
+
+/// Embeds `query`, fetches every stored chunk embedding for `source_ids`
+/// from the `Embedding` table, and ranks them by cosine similarity,
+/// returning the top `k` source ids and ranges. This is the read half of the
+/// subsystem `index_document` writes into.
+pub async fn search(
+    clickhouse: &ClickHouseConnectionInfo,
+    embedding_provider: &EmbeddingProviderConfig,
+    client: &Client,
+    api_keys: &InferenceCredentials,
+    query: &str,
+    source_ids: &[Uuid],
+    k: usize,
+) -> Result<Vec<SearchResult>, Error> {
+    let response = embedding_provider
+        .embed(
+            &EmbeddingRequest {
+                input: query.to_string().into(),
+            },
+            client,
+            api_keys,
+        )
+        .await?;
+    let query_embedding = response.embeddings.into_iter().next().ok_or_else(|| {
+        Error::new(ErrorDetails::InferenceClient {
+            message: "Query embedding returned no vectors".to_string(),
+        })
+    })?;
+    let candidates = fetch_candidates(clickhouse, source_ids).await?;
+    Ok(rank_by_similarity(&query_embedding.vector, &candidates, k))
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_text_splits_on_whitespace() {
+        let text = "the quick brown fox jumps over the lazy dog";
+        let chunks = chunk_text(text, 15);
+        for (range, chunk) in &chunks {
+            assert_eq!(&text[range.start..range.end], *chunk);
+        }
+        let reassembled: String = chunks.iter().map(|(_, chunk)| *chunk).collect();
+        assert_eq!(reassembled, text);
+    }
+
+    #[test]
+    fn test_chunk_text_empty() {
+        assert!(chunk_text("", 10).is_empty());
+    }
+
+    #[test]
+    fn test_rank_by_similarity_orders_by_score() {
+        let source_id = Uuid::now_v7();
+        let candidates = vec![
+            EmbeddingRow {
+                id: Uuid::now_v7(),
+                source_id,
+                range: ByteRange { start: 0, end: 1 },
+                vector: vec![1.0, 0.0],
+            },
+            EmbeddingRow {
+                id: Uuid::now_v7(),
+                source_id,
+                range: ByteRange { start: 1, end: 2 },
+                vector: vec![0.0, 1.0],
+            },
+        ];
+        let results = rank_by_similarity(&[1.0, 0.0], &candidates, 1);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].range, ByteRange { start: 0, end: 1 });
+        assert!((results[0].score - 1.0).abs() < 1e-6);
+    }
+}
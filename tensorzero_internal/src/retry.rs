@@ -0,0 +1,152 @@
+use std::time::Duration;
+
+use rand::Rng;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use crate::model::is_retryable_status;
+
+/// Declarative retry policy applied to a single provider's request path
+/// before the caller gives up on it (and, for models with multiple
+/// providers, rotates to the next one).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetryPolicy {
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    #[serde(default = "default_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    #[serde(default = "default_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+    #[serde(default = "default_backoff_multiplier")]
+    pub backoff_multiplier: f64,
+    /// HTTP status codes, in addition to the defaults in [`is_retryable_status`],
+    /// that should trigger a retry rather than an immediate failure.
+    #[serde(default)]
+    pub retryable_status_codes: Vec<u16>,
+}
+
+fn default_max_retries() -> u32 {
+    2
+}
+
+fn default_initial_backoff_ms() -> u64 {
+    200
+}
+
+fn default_max_backoff_ms() -> u64 {
+    5_000
+}
+
+fn default_backoff_multiplier() -> f64 {
+    2.0
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: default_max_retries(),
+            initial_backoff_ms: default_initial_backoff_ms(),
+            max_backoff_ms: default_max_backoff_ms(),
+            backoff_multiplier: default_backoff_multiplier(),
+            retryable_status_codes: Vec::new(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn is_retryable_status(&self, status: StatusCode) -> bool {
+        is_retryable_status(status) || self.retryable_status_codes.contains(&status.as_u16())
+    }
+
+    /// `min(max_backoff, initial_backoff * multiplier^attempt)` plus jitter
+    /// uniformly drawn from `[0, backoff)`.
+    fn backoff_for_attempt(&self, attempt: u32) -> Duration {
+        let scaled =
+            self.initial_backoff_ms as f64 * self.backoff_multiplier.powi(attempt as i32);
+        let backoff_ms = scaled.min(self.max_backoff_ms as f64) as u64;
+        let jitter_ms = if backoff_ms == 0 {
+            0
+        } else {
+            rand::thread_rng().gen_range(0..backoff_ms)
+        };
+        Duration::from_millis(backoff_ms + jitter_ms)
+    }
+
+    /// Retries `attempt` up to `max_retries` additional times, sleeping
+    /// between attempts. `should_retry` decides, given the error, whether
+    /// another attempt is worthwhile (e.g. a 4xx should not be retried).
+    /// Returns the first success, or the last failure once retries are
+    /// exhausted.
+    pub async fn retry<T, E, F, Fut>(&self, should_retry: impl Fn(&E) -> bool, mut attempt: F) -> Result<T, E>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T, E>>,
+    {
+        let mut last_err = None;
+        for try_number in 0..=self.max_retries {
+            match attempt().await {
+                Ok(value) => return Ok(value),
+                Err(e) => {
+                    if try_number == self.max_retries || !should_retry(&e) {
+                        return Err(e);
+                    }
+                    last_err = Some(e);
+                    tokio::time::sleep(self.backoff_for_attempt(try_number)).await;
+                }
+            }
+        }
+        // Unreachable: the loop above always returns on the final iteration.
+        Err(last_err.expect("retry loop must have recorded an error before exhausting attempts"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[tokio::test]
+    async fn test_retry_succeeds_after_transient_failures() {
+        let policy = RetryPolicy {
+            initial_backoff_ms: 1,
+            max_backoff_ms: 2,
+            ..Default::default()
+        };
+        let attempts = AtomicU32::new(0);
+        let result = policy
+            .retry(
+                |_: &&str| true,
+                || {
+                    let attempt_number = attempts.fetch_add(1, Ordering::SeqCst);
+                    async move {
+                        if attempt_number < 2 {
+                            Err("transient")
+                        } else {
+                            Ok(())
+                        }
+                    }
+                },
+            )
+            .await;
+        assert!(result.is_ok());
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_does_not_retry_when_should_retry_false() {
+        let policy = RetryPolicy::default();
+        let attempts = AtomicU32::new(0);
+        let result: Result<(), &str> = policy
+            .retry(
+                |_| false,
+                || {
+                    attempts.fetch_add(1, Ordering::SeqCst);
+                    async { Err("not retryable") }
+                },
+            )
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+}